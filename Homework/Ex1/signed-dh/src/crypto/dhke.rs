@@ -1,22 +1,36 @@
 use rand::rngs::OsRng;
-use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+use rand::RngCore;
+use x25519_dalek::{x25519, PublicKey, X25519_BASEPOINT_BYTES};
+
+use super::secret::SecretBytes;
 
 /// Ephemeral X25519 keypair for Diffie-Hellman
 pub struct DHKeypair {
-    pub sk: StaticSecret,
+    pub sk: SecretBytes<32>,
     pub pk: PublicKey,
 }
 
 impl DHKeypair {
     /// Generate a new ephemeral X25519 keypair
     pub fn keygen() -> Self {
-        let sk = StaticSecret::random_from_rng(OsRng);
-        let pk = PublicKey::from(&sk);
-        Self { sk, pk }
+        let mut sk_bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut sk_bytes);
+
+        let pk = PublicKey::from(x25519(sk_bytes, X25519_BASEPOINT_BYTES));
+        Self { sk: SecretBytes::new(sk_bytes), pk }
     }
 }
 
 /// Compute the shared secret from your secret key and their public key
-pub fn shared_secret(sk: &StaticSecret, their_pk: &PublicKey) -> [u8; 32] {
-    sk.diffie_hellman(their_pk).to_bytes()
+pub fn shared_secret(sk: &SecretBytes<32>, their_pk: &PublicKey) -> [u8; 32] {
+    x25519(**sk, *their_pk.as_bytes())
+}
+
+impl std::fmt::Debug for DHKeypair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DHKeypair")
+            .field("sk", &self.sk)
+            .field("pk", &self.pk)
+            .finish()
+    }
 }
@@ -0,0 +1,133 @@
+//! RFC 9180 single-shot HPKE built from this crate's own primitives:
+//! DHKEM(X25519, HKDF-SHA3-256) for key encapsulation, and AES-256-GCM as
+//! the AEAD. Unlike `signed_dh::Participant`, this does not run an
+//! interactive session - `seal`/`open` are one-shot, asymmetric encryption
+//! to a recipient's static X25519 public key.
+
+use x25519_dalek::{x25519, PublicKey as DHPublicKey, X25519_BASEPOINT_BYTES};
+
+use super::aead::{self, Key, Nonce};
+use super::dhke::{shared_secret, DHKeypair};
+use super::hkdf::derive_key;
+use super::secret::SecretBytes;
+
+/// ASCII suite identifier mixed into every labeled HKDF call, per RFC 9180 §4.1/5.1.
+const SUITE_ID: &[u8] = b"HPKE-v1 KEM=DHKEM(X25519,HKDF-SHA3-256) KDF=HKDF-SHA3-256 AEAD=AES-256-GCM";
+
+/// `enc`: the ephemeral DH public key produced by `seal`, sent alongside the ciphertext.
+pub type Enc = [u8; 32];
+
+/// Everything derived by the HPKE key schedule; `export` derives
+/// additional secrets from one. `seal` and `open` build their own
+/// `Context` internally and do not return it.
+pub struct Context {
+    key: Key,
+    base_nonce: Nonce,
+    exporter_secret: [u8; 32],
+}
+
+/// Seal `plaintext` to `pk_recipient`. Returns `(enc, ciphertext)`; `enc`
+/// must be sent to the recipient alongside the ciphertext.
+pub fn seal(
+    pk_recipient: &DHPublicKey,
+    info: &[u8],
+    aad: &[u8],
+    plaintext: &[u8],
+) -> (Enc, Vec<u8>) {
+    let (enc, ctx) = key_schedule_sender(pk_recipient, info);
+    let ciphertext = aead::encrypt(&ctx.key, &ctx.base_nonce, plaintext, aad)
+        .expect("AES-256-GCM encryption cannot fail");
+    (enc, ciphertext)
+}
+
+/// Open a ciphertext produced by `seal` using the recipient's static secret key.
+pub fn open(
+    sk_recipient: &SecretBytes<32>,
+    enc: &Enc,
+    info: &[u8],
+    aad: &[u8],
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, aes_gcm::Error> {
+    let ctx = key_schedule_recipient(sk_recipient, enc, info);
+    aead::decrypt(&ctx.key, &ctx.base_nonce, ciphertext, aad)
+}
+
+/// Derive additional secrets bound to this HPKE context, independent of
+/// the sealed/opened message itself (RFC 9180 §5.3).
+pub fn export(ctx: &Context, exporter_context: &[u8], length: usize) -> Vec<u8> {
+    let mut out = vec![0u8; length];
+    derive_key(&ctx.exporter_secret, None, exporter_context, &mut out);
+    out
+}
+
+fn key_schedule_sender(pk_recipient: &DHPublicKey, info: &[u8]) -> (Enc, Context) {
+    let ephemeral = DHKeypair::keygen();
+    let enc = *ephemeral.pk.as_bytes();
+
+    let dh = shared_secret(&ephemeral.sk, pk_recipient);
+    let shared_secret = extract_and_expand(&dh, &enc, pk_recipient.as_bytes());
+
+    (enc, key_schedule(&shared_secret, info))
+}
+
+fn key_schedule_recipient(sk_recipient: &SecretBytes<32>, enc: &Enc, info: &[u8]) -> Context {
+    let pk_recipient = DHPublicKey::from(x25519(**sk_recipient, X25519_BASEPOINT_BYTES));
+    let enc_pk = DHPublicKey::from(*enc);
+
+    let dh = shared_secret(sk_recipient, &enc_pk);
+    let shared_secret = extract_and_expand(&dh, enc, pk_recipient.as_bytes());
+
+    key_schedule(&shared_secret, info)
+}
+
+/// `kem_context = enc || pkR`, fed through a suite-bound labeled HKDF to
+/// produce the KEM shared secret.
+fn extract_and_expand(dh: &[u8; 32], enc: &[u8; 32], pk_recipient: &[u8; 32]) -> [u8; 32] {
+    let mut kem_context = Vec::with_capacity(64);
+    kem_context.extend_from_slice(enc);
+    kem_context.extend_from_slice(pk_recipient);
+
+    let mut info = Vec::with_capacity(SUITE_ID.len() + kem_context.len());
+    info.extend_from_slice(SUITE_ID);
+    info.extend_from_slice(&kem_context);
+
+    let mut shared_secret = [0u8; 32];
+    derive_key(dh, None, &info, &mut shared_secret);
+    shared_secret
+}
+
+/// Run the HPKE key schedule (mode_base, RFC 9180 §5.1) to produce the
+/// AEAD key, base nonce, and exporter secret.
+fn key_schedule(shared_secret: &[u8; 32], info: &[u8]) -> Context {
+    let mut salted_info = Vec::with_capacity(SUITE_ID.len() + info.len());
+    salted_info.extend_from_slice(SUITE_ID);
+    salted_info.extend_from_slice(info);
+
+    let mut key = [0u8; 32];
+    derive_key(shared_secret, None, &labeled_info(b"key", &salted_info), &mut key);
+
+    let mut base_nonce = [0u8; 12];
+    derive_key(
+        shared_secret,
+        None,
+        &labeled_info(b"base_nonce", &salted_info),
+        &mut base_nonce,
+    );
+
+    let mut exporter_secret = [0u8; 32];
+    derive_key(
+        shared_secret,
+        None,
+        &labeled_info(b"exp", &salted_info),
+        &mut exporter_secret,
+    );
+
+    Context { key, base_nonce, exporter_secret }
+}
+
+fn labeled_info(label: &[u8], salted_info: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(label.len() + salted_info.len());
+    out.extend_from_slice(label);
+    out.extend_from_slice(salted_info);
+    out
+}
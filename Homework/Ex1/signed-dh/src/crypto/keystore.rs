@@ -0,0 +1,166 @@
+//! Encrypted on-disk keystore for a `Participant`'s long-term identity.
+//!
+//! The file is JSON: public material (public keys, a key id, the scrypt
+//! cost parameters and salt) is stored in the clear, while the Ed25519 and
+//! ML-DSA secret keys are sealed together under AES-256-GCM with a key
+//! derived from the caller's passphrase via scrypt.
+
+use std::fs;
+use std::path::Path;
+
+use rand::rngs::OsRng;
+use rand::RngCore;
+use scrypt::Params as ScryptParams;
+use serde::{Deserialize, Serialize};
+
+use super::aead;
+use super::pqcrypto::DsaKeypair;
+use super::signdemo::IdentityKeypair;
+use super::signed_dh::Participant;
+
+/// scrypt cost parameters recorded alongside the salt so a keystore
+/// written with one cost setting can still be opened later even if the
+/// defaults below change.
+const SCRYPT_LOG_N: u8 = 15;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct KeystoreFile {
+    key_id: String,
+    identity_pk: [u8; 32],
+    dsa_verifying_key: Vec<u8>,
+    security_level_tag: u8,
+    scrypt_salt: [u8; 16],
+    scrypt_log_n: u8,
+    scrypt_r: u32,
+    scrypt_p: u32,
+    nonce: [u8; 12],
+    /// AES-256-GCM(identity_sk || dsa_sk), authenticated but not bound by
+    /// an AAD beyond the ciphertext itself - the cleartext fields above are
+    /// not secret and don't need to be tamper-evident here.
+    sealed_secret_keys: Vec<u8>,
+}
+
+/// Errors opening or writing a keystore file.
+#[derive(Debug)]
+pub enum KeystoreError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    /// Decryption failed - almost always a wrong passphrase
+    WrongPassphrase,
+    /// The file decrypted but didn't contain valid key material
+    CorruptKeyMaterial,
+}
+
+impl std::fmt::Display for KeystoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeystoreError::Io(e) => write!(f, "keystore I/O error: {e}"),
+            KeystoreError::Json(e) => write!(f, "keystore JSON error: {e}"),
+            KeystoreError::WrongPassphrase => write!(f, "wrong passphrase (decryption failed)"),
+            KeystoreError::CorruptKeyMaterial => write!(f, "decrypted keystore had malformed key material"),
+        }
+    }
+}
+
+impl std::error::Error for KeystoreError {}
+
+impl From<std::io::Error> for KeystoreError {
+    fn from(e: std::io::Error) -> Self {
+        KeystoreError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for KeystoreError {
+    fn from(e: serde_json::Error) -> Self {
+        KeystoreError::Json(e)
+    }
+}
+
+fn derive_keystore_key(passphrase: &str, salt: &[u8; 16], log_n: u8, r: u32, p: u32) -> [u8; 32] {
+    let params = ScryptParams::new(log_n, r, p, 32).expect("valid scrypt parameters");
+    let mut key = [0u8; 32];
+    scrypt::scrypt(passphrase.as_bytes(), salt, &params, &mut key)
+        .expect("32-byte output is valid for scrypt");
+    key
+}
+
+impl Participant {
+    /// Persist this participant's Ed25519 and ML-DSA secret keys to
+    /// `path`, encrypted under a key derived from `passphrase`.
+    pub fn save_keystore(&self, path: impl AsRef<Path>, passphrase: &str) -> Result<(), KeystoreError> {
+        let mut scrypt_salt = [0u8; 16];
+        OsRng.fill_bytes(&mut scrypt_salt);
+        let key = derive_keystore_key(passphrase, &scrypt_salt, SCRYPT_LOG_N, SCRYPT_R, SCRYPT_P);
+
+        let mut nonce = [0u8; 12];
+        OsRng.fill_bytes(&mut nonce);
+
+        let mut plaintext = self.identity.sk.to_bytes().to_vec();
+        plaintext.extend_from_slice(self.dsa.signing_key_bytes());
+
+        let sealed_secret_keys = aead::encrypt(&key, &nonce, &plaintext, b"")
+            .expect("AES-256-GCM encryption cannot fail");
+
+        let file = KeystoreFile {
+            key_id: bs58_like_id(&self.identity.pk.to_bytes()),
+            identity_pk: self.identity.pk.to_bytes(),
+            dsa_verifying_key: self.dsa.verifying_key.clone(),
+            security_level_tag: self.security_level.cipher_suite().tag(),
+            scrypt_salt,
+            scrypt_log_n: SCRYPT_LOG_N,
+            scrypt_r: SCRYPT_R,
+            scrypt_p: SCRYPT_P,
+            nonce,
+            sealed_secret_keys,
+        };
+
+        fs::write(path, serde_json::to_vec_pretty(&file)?)?;
+        Ok(())
+    }
+
+    /// Load a participant's identity from a keystore written by
+    /// `save_keystore`, generating a fresh ephemeral keypair for the new
+    /// session. Fails cleanly (`WrongPassphrase`) if decryption fails.
+    pub fn load_keystore(path: impl AsRef<Path>, passphrase: &str) -> Result<Self, KeystoreError> {
+        let file: KeystoreFile = serde_json::from_slice(&fs::read(path)?)?;
+
+        let key = derive_keystore_key(
+            passphrase,
+            &file.scrypt_salt,
+            file.scrypt_log_n,
+            file.scrypt_r,
+            file.scrypt_p,
+        );
+
+        let plaintext = aead::decrypt(&key, &file.nonce, &file.sealed_secret_keys, b"")
+            .map_err(|_| KeystoreError::WrongPassphrase)?;
+
+        let level = super::params::CipherSuite::from_tag(file.security_level_tag)
+            .ok_or(KeystoreError::CorruptKeyMaterial)?
+            .level;
+
+        let identity_seed: [u8; 32] = plaintext
+            .get(0..32)
+            .ok_or(KeystoreError::CorruptKeyMaterial)?
+            .try_into()
+            .expect("checked length");
+        let dsa_signing_key = plaintext.get(32..).ok_or(KeystoreError::CorruptKeyMaterial)?.to_vec();
+
+        let identity = IdentityKeypair::from_bytes(&identity_seed);
+        if identity.pk.to_bytes() != file.identity_pk {
+            return Err(KeystoreError::CorruptKeyMaterial);
+        }
+
+        let dsa = DsaKeypair::from_parts(level, file.dsa_verifying_key, dsa_signing_key);
+
+        Ok(Participant::with_identity_and_dsa(identity, dsa, level))
+    }
+}
+
+/// A short human-readable id derived from a public key, for display
+/// purposes only (not a cryptographic commitment).
+fn bs58_like_id(pk: &[u8; 32]) -> String {
+    crate::encode::encode_b64::b64(&pk[..8])
+}
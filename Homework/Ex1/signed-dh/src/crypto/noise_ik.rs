@@ -0,0 +1,286 @@
+//! Noise IK: a single-round-trip, identity-hiding authenticated handshake.
+//!
+//! Unlike [`super::signed_dh::Participant`], which authenticates by
+//! signing the ephemeral key with a separate Ed25519 identity, Noise IK
+//! authenticates implicitly through DH: the initiator must already know
+//! the responder's long-term X25519 static key, and the initiator's own
+//! static key travels *encrypted* inside the handshake rather than in the
+//! clear, so a passive observer cannot learn who is connecting. This
+//! follows the `Noise_IK_25519_AESGCM_SHA3256` pattern: a running
+//! "chaining key" `ck` and "handshake hash` `h`, each DH output mixed into
+//! `ck` (and hence into the next AEAD key) via HKDF, and every ciphertext
+//! bound to `h` as associated data so a tampered transcript fails to
+//! decrypt.
+
+use sha3::{Digest, Sha3_256};
+use x25519_dalek::PublicKey as DHPublicKey;
+
+use super::aead;
+use super::dhke::{shared_secret, DHKeypair};
+use super::hkdf::derive_key;
+use super::secret::SecretBytes;
+
+/// ASCII name of the handshake pattern, used to initialize `h`/`ck` so two
+/// peers running different Noise patterns can never be confused for one
+/// another even if the rest of the transcript happened to collide.
+const PROTOCOL_NAME: &[u8] = b"Noise_IK_25519_AESGCM_SHA3256";
+
+/// The message the initiator sends: its ephemeral key in the clear, its
+/// static key encrypted under the `es` DH output, and an optional payload
+/// encrypted under the `es || ss` DH output.
+pub struct NoiseIkMessage {
+    pub ephemeral_pk: DHPublicKey,
+    pub encrypted_static: Vec<u8>,
+    pub encrypted_payload: Vec<u8>,
+}
+
+/// Split send/receive keys derived from the final chaining key via
+/// `Split()`. Nonces follow the same monotonic-counter convention as
+/// [`super::signed_dh::Session`], just with two independent keys instead
+/// of one key and two salts.
+pub struct NoiseSession {
+    send_key: SecretBytes<32>,
+    recv_key: SecretBytes<32>,
+    send_sequence: u64,
+    recv_sequence: u64,
+}
+
+impl NoiseSession {
+    /// Encrypt `plaintext`, binding `aad` and advancing the send counter.
+    pub fn encrypt(&mut self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, NoiseError> {
+        let nonce = nonce_for(self.send_sequence);
+        let ciphertext = aead::encrypt(&self.send_key, &nonce, plaintext, aad)
+            .map_err(|_| NoiseError::EncryptionFailed)?;
+        self.send_sequence = self
+            .send_sequence
+            .checked_add(1)
+            .ok_or(NoiseError::SequenceExhausted)?;
+        Ok(ciphertext)
+    }
+
+    /// Decrypt a message produced by the peer's `encrypt`, requiring
+    /// records to arrive in order (no replay window, since Noise transport
+    /// messages are expected to be delivered reliably and in sequence).
+    pub fn decrypt(&mut self, ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>, NoiseError> {
+        let nonce = nonce_for(self.recv_sequence);
+        let plaintext = aead::decrypt(&self.recv_key, &nonce, ciphertext, aad)
+            .map_err(|_| NoiseError::DecryptionFailed)?;
+        self.recv_sequence = self
+            .recv_sequence
+            .checked_add(1)
+            .ok_or(NoiseError::SequenceExhausted)?;
+        Ok(plaintext)
+    }
+}
+
+fn nonce_for(sequence: u64) -> aead::Nonce {
+    let mut nonce = [0u8; 12];
+    nonce[4..].copy_from_slice(&sequence.to_be_bytes());
+    nonce
+}
+
+/// The rolling `(ck, h)` state every Noise IK party threads through the
+/// handshake: `h` commits to everything exchanged so far, `ck` is the
+/// chaining key each DH output is mixed into.
+struct SymmetricState {
+    ck: [u8; 32],
+    h: [u8; 32],
+}
+
+impl SymmetricState {
+    fn initialize() -> Self {
+        let mut h = [0u8; 32];
+        h[..PROTOCOL_NAME.len()].copy_from_slice(PROTOCOL_NAME);
+        let ck = h;
+        Self { ck, h }
+    }
+
+    /// `h = SHA3(h || data)`.
+    fn mix_hash(&mut self, data: &[u8]) {
+        let mut hasher = Sha3_256::new();
+        hasher.update(self.h);
+        hasher.update(data);
+        self.h = hasher.finalize().into();
+    }
+
+    /// `(ck, k) = HKDF-SHA3-256(salt = ck, ikm = dh_output)`, expanded to
+    /// 64 bytes: the first 32 become the new chaining key, the last 32 the
+    /// AEAD key for the next encrypted handshake payload.
+    fn mix_key(&mut self, dh_output: &[u8; 32]) -> [u8; 32] {
+        let mut okm = [0u8; 64];
+        derive_key(dh_output, Some(&self.ck), b"noise-ik-mix-key", &mut okm);
+        self.ck.copy_from_slice(&okm[..32]);
+        let mut k = [0u8; 32];
+        k.copy_from_slice(&okm[32..]);
+        k
+    }
+
+    /// Encrypt a handshake payload under `k`, with `h` as AAD, then fold
+    /// the ciphertext into `h` so the next message commits to it.
+    fn encrypt_and_hash(&mut self, k: &[u8; 32], plaintext: &[u8]) -> Result<Vec<u8>, NoiseError> {
+        let ciphertext = aead::encrypt(k, &HANDSHAKE_NONCE, plaintext, &self.h)
+            .map_err(|_| NoiseError::EncryptionFailed)?;
+        self.mix_hash(&ciphertext);
+        Ok(ciphertext)
+    }
+
+    /// Inverse of `encrypt_and_hash`.
+    fn decrypt_and_hash(&mut self, k: &[u8; 32], ciphertext: &[u8]) -> Result<Vec<u8>, NoiseError> {
+        let plaintext = aead::decrypt(k, &HANDSHAKE_NONCE, ciphertext, &self.h)
+            .map_err(|_| NoiseError::DecryptionFailed)?;
+        self.mix_hash(ciphertext);
+        Ok(plaintext)
+    }
+
+    /// `Split()`: expand the final chaining key into two transport keys.
+    fn split(&self) -> ([u8; 32], [u8; 32]) {
+        let mut okm = [0u8; 64];
+        derive_key(&self.ck, None, b"noise-ik-split", &mut okm);
+        let mut k1 = [0u8; 32];
+        let mut k2 = [0u8; 32];
+        k1.copy_from_slice(&okm[..32]);
+        k2.copy_from_slice(&okm[32..]);
+        (k1, k2)
+    }
+}
+
+/// Each handshake message is individually AEAD-keyed via `mix_key`, so
+/// within one message the nonce only ever has to be unique once; a fixed
+/// all-zero nonce per `encrypt_and_hash` call is therefore safe.
+const HANDSHAKE_NONCE: aead::Nonce = [0u8; 12];
+
+/// Run the initiator's side of the handshake in one shot: given the
+/// responder's known static public key, a fresh local static/ephemeral
+/// keypair, and an optional payload, produce the wire message and the
+/// resulting transport `NoiseSession`.
+pub fn initiate(
+    static_kp: &DHKeypair,
+    responder_static_pk: &DHPublicKey,
+    payload: &[u8],
+) -> Result<(NoiseIkMessage, NoiseSession), NoiseError> {
+    let ephemeral = DHKeypair::keygen();
+    let mut state = SymmetricState::initialize();
+    state.mix_hash(ephemeral.pk.as_bytes());
+
+    let es = shared_secret(&ephemeral.sk, responder_static_pk);
+    let k = state.mix_key(&es);
+    let encrypted_static = state.encrypt_and_hash(&k, static_kp.pk.as_bytes())?;
+
+    let ss = shared_secret(&static_kp.sk, responder_static_pk);
+    let k = state.mix_key(&ss);
+    let encrypted_payload = state.encrypt_and_hash(&k, payload)?;
+
+    let (initiator_send, initiator_recv) = state.split();
+    let session = NoiseSession {
+        send_key: SecretBytes::new(initiator_send),
+        recv_key: SecretBytes::new(initiator_recv),
+        send_sequence: 0,
+        recv_sequence: 0,
+    };
+
+    Ok((
+        NoiseIkMessage { ephemeral_pk: ephemeral.pk, encrypted_static, encrypted_payload },
+        session,
+    ))
+}
+
+/// Run the responder's side: recover the initiator's ephemeral and static
+/// keys from `message`, verifying both AEAD tags, and derive the same
+/// transport session (with send/receive swapped relative to the initiator).
+pub fn respond(
+    static_kp: &DHKeypair,
+    message: &NoiseIkMessage,
+) -> Result<(DHPublicKey, Vec<u8>, NoiseSession), NoiseError> {
+    let mut state = SymmetricState::initialize();
+    state.mix_hash(message.ephemeral_pk.as_bytes());
+
+    let se = shared_secret(&static_kp.sk, &message.ephemeral_pk);
+    let k = state.mix_key(&se);
+    let initiator_static_bytes = state.decrypt_and_hash(&k, &message.encrypted_static)?;
+    let initiator_static_pk = DHPublicKey::from(
+        <[u8; 32]>::try_from(initiator_static_bytes.as_slice())
+            .map_err(|_| NoiseError::DecryptionFailed)?,
+    );
+
+    let ss = shared_secret(&static_kp.sk, &initiator_static_pk);
+    let k = state.mix_key(&ss);
+    let payload = state.decrypt_and_hash(&k, &message.encrypted_payload)?;
+
+    let (initiator_send, initiator_recv) = state.split();
+    // The responder's send/receive keys are the initiator's, swapped.
+    let session = NoiseSession {
+        send_key: SecretBytes::new(initiator_recv),
+        recv_key: SecretBytes::new(initiator_send),
+        send_sequence: 0,
+        recv_sequence: 0,
+    };
+
+    Ok((initiator_static_pk, payload, session))
+}
+
+/// Errors during a Noise IK handshake or subsequent transport encryption.
+#[derive(Debug, Clone, Copy)]
+pub enum NoiseError {
+    /// A handshake or transport ciphertext failed to encrypt (should not
+    /// happen absent a programming error; kept for symmetry with decrypt).
+    EncryptionFailed,
+    /// A handshake or transport AEAD tag failed to verify - either the
+    /// peer's identity doesn't match what the initiator expected, or the
+    /// message was tampered with in transit.
+    DecryptionFailed,
+    /// The 64-bit transport nonce counter is exhausted; the session must
+    /// be re-established.
+    SequenceExhausted,
+}
+
+impl std::fmt::Display for NoiseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NoiseError::EncryptionFailed => write!(f, "Encryption failed"),
+            NoiseError::DecryptionFailed => write!(f, "Decryption failed (auth tag mismatch)"),
+            NoiseError::SequenceExhausted => write!(f, "Transport nonce counter exhausted; session must be re-established"),
+        }
+    }
+}
+
+impl std::error::Error for NoiseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_noise_ik_handshake_and_transport() {
+        let responder_static = DHKeypair::keygen();
+        let initiator_static = DHKeypair::keygen();
+
+        let (message, mut initiator_session) =
+            initiate(&initiator_static, &responder_static.pk, b"hello responder").unwrap();
+
+        let (recovered_initiator_pk, payload, mut responder_session) =
+            respond(&responder_static, &message).unwrap();
+
+        assert_eq!(recovered_initiator_pk.as_bytes(), initiator_static.pk.as_bytes());
+        assert_eq!(payload, b"hello responder");
+
+        let ciphertext = initiator_session.encrypt(b"transport message", b"").unwrap();
+        let decrypted = responder_session.decrypt(&ciphertext, b"").unwrap();
+        assert_eq!(decrypted, b"transport message");
+    }
+
+    #[test]
+    fn test_noise_ik_rejects_wrong_responder_static_key() {
+        let real_responder_static = DHKeypair::keygen();
+        let wrong_responder_static = DHKeypair::keygen();
+        let initiator_static = DHKeypair::keygen();
+
+        // Initiator thinks it's talking to `wrong_responder_static`.
+        let (message, _initiator_session) =
+            initiate(&initiator_static, &wrong_responder_static.pk, b"hi").unwrap();
+
+        // The real responder can't recover a matching `es`, so decrypting
+        // the initiator's static key fails.
+        let result = respond(&real_responder_static, &message);
+        assert!(matches!(result, Err(NoiseError::DecryptionFailed)));
+    }
+}
@@ -0,0 +1,123 @@
+//! Security parameters for the hybrid classical + post-quantum handshake.
+//!
+//! `SecurityLevel` picks a matched pair of NIST PQC algorithms (ML-KEM for
+//! key encapsulation, ML-DSA for signatures) and reports the byte sizes of
+//! their keys/ciphertexts/signatures so callers can size buffers without
+//! depending on the underlying crates directly.
+
+/// NIST security category for the post-quantum primitives used alongside
+/// the classical X25519/Ed25519 keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecurityLevel {
+    /// ML-KEM-512 + ML-DSA-44 (NIST category 1)
+    Level1,
+    /// ML-KEM-768 + ML-DSA-65 (NIST category 3)
+    Level3,
+    /// ML-KEM-1024 + ML-DSA-87 (NIST category 5)
+    Level5,
+}
+
+/// The concrete KEM/signature pair negotiated for a given `SecurityLevel`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CipherSuite {
+    pub level: SecurityLevel,
+    pub kem: &'static str,
+    pub signature: &'static str,
+}
+
+/// Byte sizes of the PQ key material for a `SecurityLevel`, used to
+/// pre-size buffers for encoding/decoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeySizes {
+    pub kem_encapsulation_key: usize,
+    pub kem_decapsulation_key: usize,
+    pub kem_ciphertext: usize,
+    pub kem_shared_secret: usize,
+    pub dsa_signing_key: usize,
+    pub dsa_verifying_key: usize,
+    pub dsa_signature: usize,
+}
+
+impl SecurityLevel {
+    /// The `CipherSuite` this level negotiates.
+    pub fn cipher_suite(self) -> CipherSuite {
+        match self {
+            SecurityLevel::Level1 => CipherSuite {
+                level: self,
+                kem: "ML-KEM-512",
+                signature: "ML-DSA-44",
+            },
+            SecurityLevel::Level3 => CipherSuite {
+                level: self,
+                kem: "ML-KEM-768",
+                signature: "ML-DSA-65",
+            },
+            SecurityLevel::Level5 => CipherSuite {
+                level: self,
+                kem: "ML-KEM-1024",
+                signature: "ML-DSA-87",
+            },
+        }
+    }
+
+    /// Byte sizes of every key/ciphertext/signature at this level.
+    pub fn key_sizes(self) -> KeySizes {
+        match self {
+            SecurityLevel::Level1 => KeySizes {
+                kem_encapsulation_key: 800,
+                kem_decapsulation_key: 1632,
+                kem_ciphertext: 768,
+                kem_shared_secret: 32,
+                dsa_signing_key: 2560,
+                dsa_verifying_key: 1312,
+                dsa_signature: 2420,
+            },
+            SecurityLevel::Level3 => KeySizes {
+                kem_encapsulation_key: 1184,
+                kem_decapsulation_key: 2400,
+                kem_ciphertext: 1088,
+                kem_shared_secret: 32,
+                dsa_signing_key: 4032,
+                dsa_verifying_key: 1952,
+                dsa_signature: 3309,
+            },
+            SecurityLevel::Level5 => KeySizes {
+                kem_encapsulation_key: 1568,
+                kem_decapsulation_key: 3168,
+                kem_ciphertext: 1568,
+                kem_shared_secret: 32,
+                dsa_signing_key: 4896,
+                dsa_verifying_key: 2592,
+                dsa_signature: 4627,
+            },
+        }
+    }
+}
+
+impl Default for SecurityLevel {
+    fn default() -> Self {
+        SecurityLevel::Level3
+    }
+}
+
+impl CipherSuite {
+    /// A single-byte tag identifying this suite on the wire.
+    pub fn tag(self) -> u8 {
+        match self.level {
+            SecurityLevel::Level1 => 1,
+            SecurityLevel::Level3 => 3,
+            SecurityLevel::Level5 => 5,
+        }
+    }
+
+    /// Recover the `CipherSuite` for a wire tag produced by `tag()`.
+    pub fn from_tag(tag: u8) -> Option<Self> {
+        let level = match tag {
+            1 => SecurityLevel::Level1,
+            3 => SecurityLevel::Level3,
+            5 => SecurityLevel::Level5,
+            _ => return None,
+        };
+        Some(level.cipher_suite())
+    }
+}
@@ -0,0 +1,209 @@
+//! ML-KEM encapsulation and ML-DSA signatures, sized per [`SecurityLevel`].
+//!
+//! Keys are stored as opaque byte buffers so the rest of the crate does not
+//! need to depend on which concrete ML-KEM/ML-DSA parameter set is in play
+//! for a given session; the `SecurityLevel` tag on each keypair is enough
+//! to pick the right algorithm back out when signing/verifying/(de)capsulating.
+
+use ml_dsa::signature::{Signer, Verifier};
+use ml_dsa::{KeyGen, MlDsa44, MlDsa65, MlDsa87};
+use ml_kem::{Encapsulate, Decapsulate, EncodedSizeUser, KemCore, MlKem512, MlKem768, MlKem1024};
+use rand::rngs::OsRng;
+
+use super::params::SecurityLevel;
+
+/// An ML-KEM encapsulation/decapsulation keypair.
+pub struct KemKeypair {
+    pub level: SecurityLevel,
+    pub encapsulation_key: Vec<u8>,
+    decapsulation_key: Vec<u8>,
+}
+
+impl KemKeypair {
+    /// Generate a fresh ML-KEM keypair sized for `level`.
+    pub fn keygen(level: SecurityLevel) -> Self {
+        match level {
+            SecurityLevel::Level1 => {
+                let (dk, ek) = MlKem512::generate(&mut OsRng);
+                Self {
+                    level,
+                    encapsulation_key: ek.as_bytes().to_vec(),
+                    decapsulation_key: dk.as_bytes().to_vec(),
+                }
+            }
+            SecurityLevel::Level3 => {
+                let (dk, ek) = MlKem768::generate(&mut OsRng);
+                Self {
+                    level,
+                    encapsulation_key: ek.as_bytes().to_vec(),
+                    decapsulation_key: dk.as_bytes().to_vec(),
+                }
+            }
+            SecurityLevel::Level5 => {
+                let (dk, ek) = MlKem1024::generate(&mut OsRng);
+                Self {
+                    level,
+                    encapsulation_key: ek.as_bytes().to_vec(),
+                    decapsulation_key: dk.as_bytes().to_vec(),
+                }
+            }
+        }
+    }
+
+    /// Decapsulate a ciphertext produced against `self.encapsulation_key`,
+    /// recovering the ML-KEM shared secret.
+    pub fn decapsulate(&self, ciphertext: &[u8]) -> Vec<u8> {
+        match self.level {
+            SecurityLevel::Level1 => {
+                let dk = <MlKem512 as KemCore>::DecapsulationKey::from_bytes(
+                    self.decapsulation_key.as_slice().into(),
+                );
+                let ct = ciphertext.into();
+                dk.decapsulate(ct).expect("valid ML-KEM-512 ciphertext").to_vec()
+            }
+            SecurityLevel::Level3 => {
+                let dk = <MlKem768 as KemCore>::DecapsulationKey::from_bytes(
+                    self.decapsulation_key.as_slice().into(),
+                );
+                let ct = ciphertext.into();
+                dk.decapsulate(ct).expect("valid ML-KEM-768 ciphertext").to_vec()
+            }
+            SecurityLevel::Level5 => {
+                let dk = <MlKem1024 as KemCore>::DecapsulationKey::from_bytes(
+                    self.decapsulation_key.as_slice().into(),
+                );
+                let ct = ciphertext.into();
+                dk.decapsulate(ct).expect("valid ML-KEM-1024 ciphertext").to_vec()
+            }
+        }
+    }
+}
+
+/// Encapsulate against a peer's ML-KEM encapsulation key, returning
+/// `(ciphertext, shared_secret)`.
+pub fn kem_encapsulate(level: SecurityLevel, encapsulation_key: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    match level {
+        SecurityLevel::Level1 => {
+            let ek = <MlKem512 as KemCore>::EncapsulationKey::from_bytes(
+                encapsulation_key.into(),
+            );
+            let (ct, ss) = ek.encapsulate(&mut OsRng).expect("encapsulation cannot fail");
+            (ct.to_vec(), ss.to_vec())
+        }
+        SecurityLevel::Level3 => {
+            let ek = <MlKem768 as KemCore>::EncapsulationKey::from_bytes(
+                encapsulation_key.into(),
+            );
+            let (ct, ss) = ek.encapsulate(&mut OsRng).expect("encapsulation cannot fail");
+            (ct.to_vec(), ss.to_vec())
+        }
+        SecurityLevel::Level5 => {
+            let ek = <MlKem1024 as KemCore>::EncapsulationKey::from_bytes(
+                encapsulation_key.into(),
+            );
+            let (ct, ss) = ek.encapsulate(&mut OsRng).expect("encapsulation cannot fail");
+            (ct.to_vec(), ss.to_vec())
+        }
+    }
+}
+
+/// An ML-DSA signing/verifying keypair.
+pub struct DsaKeypair {
+    pub level: SecurityLevel,
+    pub verifying_key: Vec<u8>,
+    pub(crate) signing_key: Vec<u8>,
+}
+
+impl DsaKeypair {
+    /// Reconstruct a keypair from its raw encoded signing and verifying
+    /// keys, e.g. ones recovered from an encrypted keystore.
+    pub fn from_parts(level: SecurityLevel, verifying_key: Vec<u8>, signing_key: Vec<u8>) -> Self {
+        Self { level, verifying_key, signing_key }
+    }
+
+    /// The encoded signing key bytes, for callers (like the keystore) that
+    /// need to persist it under their own encryption.
+    pub fn signing_key_bytes(&self) -> &[u8] {
+        &self.signing_key
+    }
+
+    /// Generate a fresh ML-DSA keypair sized for `level`.
+    pub fn keygen(level: SecurityLevel) -> Self {
+        match level {
+            SecurityLevel::Level1 => {
+                let kp = MlDsa44::key_gen(&mut OsRng);
+                Self {
+                    level,
+                    verifying_key: kp.verifying_key().encode().to_vec(),
+                    signing_key: kp.signing_key().encode().to_vec(),
+                }
+            }
+            SecurityLevel::Level3 => {
+                let kp = MlDsa65::key_gen(&mut OsRng);
+                Self {
+                    level,
+                    verifying_key: kp.verifying_key().encode().to_vec(),
+                    signing_key: kp.signing_key().encode().to_vec(),
+                }
+            }
+            SecurityLevel::Level5 => {
+                let kp = MlDsa87::key_gen(&mut OsRng);
+                Self {
+                    level,
+                    verifying_key: kp.verifying_key().encode().to_vec(),
+                    signing_key: kp.signing_key().encode().to_vec(),
+                }
+            }
+        }
+    }
+
+    /// Sign `message` (the handshake transcript), returning the encoded signature.
+    pub fn sign(&self, message: &[u8]) -> Vec<u8> {
+        match self.level {
+            SecurityLevel::Level1 => {
+                let sk = <MlDsa44 as ml_dsa::MlDsaParams>::SigningKey::decode(
+                    self.signing_key.as_slice().into(),
+                );
+                sk.sign(message).encode().to_vec()
+            }
+            SecurityLevel::Level3 => {
+                let sk = <MlDsa65 as ml_dsa::MlDsaParams>::SigningKey::decode(
+                    self.signing_key.as_slice().into(),
+                );
+                sk.sign(message).encode().to_vec()
+            }
+            SecurityLevel::Level5 => {
+                let sk = <MlDsa87 as ml_dsa::MlDsaParams>::SigningKey::decode(
+                    self.signing_key.as_slice().into(),
+                );
+                sk.sign(message).encode().to_vec()
+            }
+        }
+    }
+}
+
+/// Verify an ML-DSA signature against an encoded verifying key.
+pub fn dsa_verify(
+    level: SecurityLevel,
+    verifying_key: &[u8],
+    message: &[u8],
+    signature: &[u8],
+) -> bool {
+    match level {
+        SecurityLevel::Level1 => {
+            let vk = <MlDsa44 as ml_dsa::MlDsaParams>::VerifyingKey::decode(verifying_key.into());
+            let Ok(sig) = signature.try_into() else { return false };
+            vk.verify(message, &sig).is_ok()
+        }
+        SecurityLevel::Level3 => {
+            let vk = <MlDsa65 as ml_dsa::MlDsaParams>::VerifyingKey::decode(verifying_key.into());
+            let Ok(sig) = signature.try_into() else { return false };
+            vk.verify(message, &sig).is_ok()
+        }
+        SecurityLevel::Level5 => {
+            let vk = <MlDsa87 as ml_dsa::MlDsaParams>::VerifyingKey::decode(verifying_key.into());
+            let Ok(sig) = signature.try_into() else { return false };
+            vk.verify(message, &sig).is_ok()
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! Sliding-window replay protection for sequence-numbered records.
+
+const DEFAULT_WINDOW: u64 = 64;
+
+/// Tracks the highest sequence number accepted so far plus a bitmap of the
+/// trailing `window` sequence numbers, so records may arrive out of order
+/// within the window without being treated as replays - but a sequence
+/// number seen twice, or older than the window, is always rejected.
+pub struct ReplayWindow {
+    window: u64,
+    highest: Option<u64>,
+    seen: u64,
+}
+
+impl ReplayWindow {
+    pub fn new() -> Self {
+        Self::with_window(DEFAULT_WINDOW)
+    }
+
+    /// `window` is clamped to `1..=64`, the width of the `seen` bitmap.
+    pub fn with_window(window: u64) -> Self {
+        Self { window: window.clamp(1, 64), highest: None, seen: 0 }
+    }
+
+    /// Would `seq` be accepted? Does not record it - call `record` only
+    /// once the record has also authenticated successfully.
+    pub fn check(&self, seq: u64) -> bool {
+        match self.highest {
+            None => true,
+            Some(highest) if seq > highest => true,
+            Some(highest) => {
+                let age = highest - seq;
+                age < self.window && self.seen & (1 << age) == 0
+            }
+        }
+    }
+
+    /// Record `seq` as accepted.
+    pub fn record(&mut self, seq: u64) {
+        match self.highest {
+            None => {
+                self.highest = Some(seq);
+                self.seen = 1;
+            }
+            Some(highest) if seq > highest => {
+                let shift = seq - highest;
+                self.seen = if shift >= self.window { 1 } else { (self.seen << shift) | 1 };
+                self.highest = Some(seq);
+            }
+            Some(highest) => {
+                let age = highest - seq;
+                self.seen |= 1 << age;
+            }
+        }
+    }
+}
+
+impl Default for ReplayWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,60 @@
+//! Zero-on-drop wrapper for secret key material.
+//!
+//! `SecretBytes<N>` behaves like `[u8; N]` for reads but scrubs itself on
+//! `Drop` with a volatile write plus a compiler fence, so the scrub is not
+//! optimized away even though nothing reads the zeroed buffer afterwards.
+
+use std::fmt;
+use std::ops::Deref;
+use std::sync::atomic::{compiler_fence, Ordering};
+
+/// `N` bytes of secret material that are zeroized when dropped.
+///
+/// Intentionally not `Copy` (and not `Clone`) so a `SecretBytes` can't be
+/// silently duplicated, leaving a live copy behind after the original is
+/// scrubbed.
+pub struct SecretBytes<const N: usize>([u8; N]);
+
+impl<const N: usize> SecretBytes<N> {
+    pub fn new(bytes: [u8; N]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl<const N: usize> Deref for SecretBytes<N> {
+    type Target = [u8; N];
+
+    fn deref(&self) -> &[u8; N] {
+        &self.0
+    }
+}
+
+impl<const N: usize> Drop for SecretBytes<N> {
+    fn drop(&mut self) {
+        for byte in self.0.iter_mut() {
+            // SAFETY: `byte` is a valid `&mut u8` for the duration of this write.
+            unsafe { std::ptr::write_volatile(byte, 0) };
+        }
+        compiler_fence(Ordering::SeqCst);
+    }
+}
+
+impl<const N: usize> fmt::Debug for SecretBytes<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretBytes<{N}>(REDACTED)")
+    }
+}
+
+impl<const N: usize> PartialEq for SecretBytes<N> {
+    /// Constant-time comparison so tests and callers can't be used to turn
+    /// this secret into a timing oracle.
+    fn eq(&self, other: &Self) -> bool {
+        let mut diff = 0u8;
+        for (a, b) in self.0.iter().zip(other.0.iter()) {
+            diff |= a ^ b;
+        }
+        diff == 0
+    }
+}
+
+impl<const N: usize> Eq for SecretBytes<N> {}
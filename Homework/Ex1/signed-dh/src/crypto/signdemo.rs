@@ -15,6 +15,14 @@ impl IdentityKeypair {
         Self { sk, pk }
     }
 
+    /// Reconstruct a keypair from a raw 32-byte Ed25519 seed, e.g. one
+    /// recovered from an encrypted keystore.
+    pub fn from_bytes(seed: &[u8; 32]) -> Self {
+        let sk = SigningKey::from_bytes(seed);
+        let pk = sk.verifying_key();
+        Self { sk, pk }
+    }
+
     /// Sign arbitrary data
     pub fn sign(&self, data: &[u8]) -> Signature {
         self.sk.sign(data)
@@ -8,13 +8,20 @@
 //! 5. HKDF derives an encryption key from the shared secret
 //! 6. AES-256-GCM provides authenticated encryption
 
+use std::cell::{Cell, RefCell};
+
 use ed25519_dalek::{Signature, VerifyingKey};
+use serde::{Deserialize, Serialize};
 use x25519_dalek::PublicKey as DHPublicKey;
 
 use super::signdemo::{verify_with_pk, IdentityKeypair};
 use super::dhke::{DHKeypair, shared_secret};
-use super::hkdf::derive_aes256gcm_key;
-use super::aead::{self, Key, Nonce};
+use super::hkdf::{derive_aes256gcm_key, derive_key};
+use super::aead::{self, Nonce};
+use super::params::SecurityLevel;
+use super::pqcrypto::{dsa_verify, kem_encapsulate, DsaKeypair, KemKeypair};
+use super::replay::ReplayWindow;
+use super::secret::SecretBytes;
 
 /// A participant in the Signed DH protocol
 pub struct Participant {
@@ -24,6 +31,16 @@ pub struct Participant {
     pub ephemeral: DHKeypair,
     /// Signature over our ephemeral public key
     pub sig: Signature,
+    /// Post-quantum security level negotiated for the hybrid handshake
+    pub security_level: SecurityLevel,
+    /// ML-KEM encapsulation/decapsulation keypair, sized per `security_level`
+    pub kem: KemKeypair,
+    /// ML-DSA signing keypair, sized per `security_level`
+    pub dsa: DsaKeypair,
+    /// Long-term X25519 static keypair used for 3DH/X3DH session
+    /// establishment (see [`Participant::establish_session_3dh`]), kept
+    /// separate from the Ed25519 `identity` key used by the signed flow.
+    pub static_dh: DHKeypair,
 }
 
 /// Message sent during the key exchange (what you'd send over the wire)
@@ -36,31 +53,209 @@ pub struct KeyExchangeMessage {
     pub signature: Signature,
 }
 
-/// An established session with derived keys
+/// Message sent for [`Participant::establish_session_3dh`]: both the
+/// static and ephemeral X25519 public keys, neither of which is signed -
+/// the 3DH agreement authenticates the peer implicitly instead.
+#[derive(Clone, Copy)]
+pub struct ThreeDhMessage {
+    pub static_pk: DHPublicKey,
+    pub ephemeral_pk: DHPublicKey,
+}
+
+/// Plain-byte mirror of `KeyExchangeMessage` used for (de)serialization -
+/// `VerifyingKey`/`PublicKey`/`Signature` don't implement `serde` traits,
+/// so this is what actually gets encoded.
+#[derive(Serialize, Deserialize)]
+struct KeyExchangeMessageWire {
+    identity_pk: [u8; 32],
+    ephemeral_pk: [u8; 32],
+    signature: [u8; 64],
+}
+
+impl Serialize for KeyExchangeMessage {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        KeyExchangeMessageWire {
+            identity_pk: self.identity_pk.to_bytes(),
+            ephemeral_pk: *self.ephemeral_pk.as_bytes(),
+            signature: self.signature.to_bytes(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyExchangeMessage {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let wire = KeyExchangeMessageWire::deserialize(deserializer)?;
+        Ok(KeyExchangeMessage {
+            identity_pk: VerifyingKey::from_bytes(&wire.identity_pk)
+                .map_err(serde::de::Error::custom)?,
+            ephemeral_pk: DHPublicKey::from(wire.ephemeral_pk),
+            signature: Signature::from_bytes(&wire.signature),
+        })
+    }
+}
+
+/// An encrypted message ready to go over the wire: the sequence-prefixed
+/// ciphertext produced by `Session::encrypt`, plus the associated data it
+/// was bound to (so the recipient can reproduce it for `Session::decrypt`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedEnvelope {
+    pub ciphertext: Vec<u8>,
+    pub aad: Vec<u8>,
+}
+
+impl EncryptedEnvelope {
+    pub fn new(ciphertext: Vec<u8>, aad: Vec<u8>) -> Self {
+        Self { ciphertext, aad }
+    }
+}
+
+/// Upper bound on a padded plaintext, independent of which
+/// `PaddingPolicy` is in effect.
+pub const PADDED_MAX_SIZE: usize = 64 * 1024;
+
+/// How a message's true length is hidden from an observer of ciphertext
+/// size: the plaintext is prefixed with its true length (so `decrypt` can
+/// recover it) and padded out to the policy's target size before
+/// encryption.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaddingPolicy {
+    /// No padding beyond the 4-byte length prefix.
+    None,
+    /// Round the framed (length-prefixed) size up to the next multiple of
+    /// `bucket` bytes.
+    FixedBucket(usize),
+    /// Round the framed size up to the next power of two.
+    PowerOfTwo,
+}
+
+impl PaddingPolicy {
+    fn padded_len(self, framed_len: usize) -> Result<usize, SessionError> {
+        let target = match self {
+            PaddingPolicy::None => framed_len,
+            PaddingPolicy::FixedBucket(bucket) => {
+                let bucket = bucket.max(1);
+                framed_len.div_ceil(bucket) * bucket
+            }
+            PaddingPolicy::PowerOfTwo => framed_len.next_power_of_two(),
+        };
+        if target > PADDED_MAX_SIZE {
+            return Err(SessionError::PaddedMessageTooLarge);
+        }
+        Ok(target)
+    }
+}
+
+/// An established session with derived keys.
+///
+/// Nonces are managed internally: each direction gets its own 4-byte salt
+/// derived from `key`, and the 12-byte AEAD nonce is `salt || be64(seq)`
+/// with `seq` a monotonically increasing per-direction counter. This rules
+/// out the nonce reuse that comes from callers supplying their own nonces.
 pub struct Session {
     /// Derived AES-256-GCM key
-    pub key: Key,
-    /// Raw shared secret (for debugging/display)
-    pub shared_secret: [u8; 32],
+    pub key: SecretBytes<32>,
+    /// Raw X25519 shared secret
+    pub shared_secret: SecretBytes<32>,
+    send_salt: [u8; 4],
+    recv_salt: [u8; 4],
+    send_sequence: Cell<u64>,
+    recv_window: RefCell<ReplayWindow>,
+    padding: Cell<PaddingPolicy>,
+}
+
+impl std::fmt::Debug for Session {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Session")
+            .field("key", &self.key)
+            .field("shared_secret", &self.shared_secret)
+            .finish()
+    }
 }
 
+/// Build a `Session`, picking which of the two HKDF-derived salts is the
+/// send salt vs. the receive salt by comparing ephemeral public keys - the
+/// same tie-break both peers can compute independently, so Alice's send
+/// salt is always Bob's receive salt and vice versa.
+fn new_session(
+    key: [u8; 32],
+    shared_secret: [u8; 32],
+    self_pk: &DHPublicKey,
+    peer_pk: &DHPublicKey,
+) -> Session {
+    let mut salt_lo = [0u8; 4];
+    let mut salt_hi = [0u8; 4];
+    derive_key(&key, None, b"nonce-salt-lo", &mut salt_lo);
+    derive_key(&key, None, b"nonce-salt-hi", &mut salt_hi);
+
+    let (send_salt, recv_salt) = if self_pk.as_bytes() < peer_pk.as_bytes() {
+        (salt_lo, salt_hi)
+    } else {
+        (salt_hi, salt_lo)
+    };
+
+    Session {
+        key: SecretBytes::new(key),
+        shared_secret: SecretBytes::new(shared_secret),
+        send_salt,
+        recv_salt,
+        send_sequence: Cell::new(0),
+        recv_window: RefCell::new(ReplayWindow::new()),
+        padding: Cell::new(PaddingPolicy::None),
+    }
+}
+
+fn nonce_for(salt: &[u8; 4], sequence: u64) -> Nonce {
+    let mut nonce = [0u8; 12];
+    nonce[..4].copy_from_slice(salt);
+    nonce[4..].copy_from_slice(&sequence.to_be_bytes());
+    nonce
+}
+
+
 impl Participant {
-    /// Create a new participant with fresh identity and ephemeral keys
+    /// Create a new participant with fresh identity and ephemeral keys,
+    /// at the default `SecurityLevel::Level3` hybrid strength.
     pub fn new() -> Self {
+        Self::with_security_level(SecurityLevel::default())
+    }
+
+    /// Create a new participant whose PQ keys (ML-KEM, ML-DSA) are sized
+    /// for `level`; the classical Ed25519/X25519 keys are unaffected.
+    pub fn with_security_level(level: SecurityLevel) -> Self {
         let identity = IdentityKeypair::keygen();
         let ephemeral = DHKeypair::keygen();
-        
+
         // Sign our own ephemeral public key
         let sig = identity.sign(ephemeral.pk.as_bytes());
-        
-        Self { identity, ephemeral, sig }
+
+        let kem = KemKeypair::keygen(level);
+        let dsa = DsaKeypair::keygen(level);
+        let static_dh = DHKeypair::keygen();
+
+        Self { identity, ephemeral, sig, security_level: level, kem, dsa, static_dh }
     }
 
     /// Create from existing identity (generate new ephemeral)
     pub fn with_identity(identity: IdentityKeypair) -> Self {
         let ephemeral = DHKeypair::keygen();
         let sig = identity.sign(ephemeral.pk.as_bytes());
-        Self { identity, ephemeral, sig }
+        let level = SecurityLevel::default();
+        let kem = KemKeypair::keygen(level);
+        let dsa = DsaKeypair::keygen(level);
+        let static_dh = DHKeypair::keygen();
+        Self { identity, ephemeral, sig, security_level: level, kem, dsa, static_dh }
+    }
+
+    /// Create from an existing identity and ML-DSA keypair (e.g. both
+    /// recovered from an encrypted keystore), generating fresh ephemeral
+    /// and ML-KEM keys for the new session.
+    pub fn with_identity_and_dsa(identity: IdentityKeypair, dsa: DsaKeypair, level: SecurityLevel) -> Self {
+        let ephemeral = DHKeypair::keygen();
+        let sig = identity.sign(ephemeral.pk.as_bytes());
+        let kem = KemKeypair::keygen(level);
+        let static_dh = DHKeypair::keygen();
+        Self { identity, ephemeral, sig, security_level: level, kem, dsa, static_dh }
     }
 
     /// Get the key exchange message to send to the peer
@@ -95,11 +290,205 @@ impl Participant {
         // 3. Derive encryption key via HKDF
         let key = derive_aes256gcm_key(&ss, None, info);
 
-        Ok(Session {
-            key,
-            shared_secret: ss,
-        })
+        Ok(new_session(key, ss, &self.ephemeral.pk, &peer_msg.ephemeral_pk))
+    }
+
+    /// Get the 3DH message to send to the peer: both this participant's
+    /// long-term static X25519 key and its ephemeral, unsigned - the DH
+    /// agreement itself is what authenticates, so there is nothing to sign.
+    pub fn three_dh_message(&self) -> ThreeDhMessage {
+        ThreeDhMessage {
+            static_pk: self.static_dh.pk,
+            ephemeral_pk: self.ephemeral.pk,
+        }
+    }
+
+    /// Establish a session via 3DH (as in X3DH): combine
+    /// `DH(static_self, ephemeral_peer)`, `DH(ephemeral_self, static_peer)`,
+    /// `DH(ephemeral_self, ephemeral_peer)` and `DH(static_self, static_peer)`
+    /// into one HKDF input. Because X25519 is commutative, the two "cross"
+    /// terms computed by each side are each other's mirror image, so they
+    /// are sorted by comparing the two static public keys before
+    /// concatenating - the same canonical-ordering trick [`new_session`]
+    /// uses for its nonce salts - ensuring both peers build an identical
+    /// IKM without needing an explicit initiator/responder role. A peer
+    /// that doesn't hold the expected static private key ends up with a
+    /// shared secret that silently disagrees rather than failing a
+    /// signature check, giving implicit mutual authentication.
+    pub fn establish_session_3dh(&self, peer_msg: &ThreeDhMessage, info: &[u8]) -> Session {
+        let dh_se = shared_secret(&self.static_dh.sk, &peer_msg.ephemeral_pk);
+        let dh_es = shared_secret(&self.ephemeral.sk, &peer_msg.static_pk);
+        let dh_ee = shared_secret(&self.ephemeral.sk, &peer_msg.ephemeral_pk);
+        let dh_ss = shared_secret(&self.static_dh.sk, &peer_msg.static_pk);
+
+        let self_is_lower = self.static_dh.pk.as_bytes() < peer_msg.static_pk.as_bytes();
+        let (first_cross, second_cross) = if self_is_lower { (dh_se, dh_es) } else { (dh_es, dh_se) };
+        let (lower_static_pk, higher_static_pk) = if self_is_lower {
+            (self.static_dh.pk.as_bytes(), peer_msg.static_pk.as_bytes())
+        } else {
+            (peer_msg.static_pk.as_bytes(), self.static_dh.pk.as_bytes())
+        };
+
+        let mut ikm = Vec::with_capacity(32 * 4);
+        ikm.extend_from_slice(&first_cross);
+        ikm.extend_from_slice(&second_cross);
+        ikm.extend_from_slice(&dh_ee);
+        ikm.extend_from_slice(&dh_ss);
+
+        let mut bound_info = Vec::with_capacity(info.len() + 64);
+        bound_info.extend_from_slice(info);
+        bound_info.extend_from_slice(lower_static_pk);
+        bound_info.extend_from_slice(higher_static_pk);
+
+        let key = derive_aes256gcm_key(&ikm, None, &bound_info);
+        new_session(key, dh_ee, &self.ephemeral.pk, &peer_msg.ephemeral_pk)
+    }
+
+    /// Build the initiator's half of the hybrid handshake: a fresh X25519
+    /// ephemeral key plus this participant's ML-KEM encapsulation key,
+    /// signed with ML-DSA over their concatenation.
+    pub fn hybrid_init_message(&self) -> HybridInitMessage {
+        let x25519_pk = self.ephemeral.pk;
+        let transcript = hybrid_init_transcript(&x25519_pk, &self.kem.encapsulation_key);
+        let signature = self.dsa.sign(&transcript);
+
+        HybridInitMessage {
+            level: self.security_level,
+            identity_pk: self.dsa.verifying_key.clone(),
+            x25519_pk,
+            kem_encapsulation_key: self.kem.encapsulation_key.clone(),
+            signature,
+        }
+    }
+
+    /// Responder side of the hybrid handshake: verify the initiator's
+    /// ML-DSA signature, encapsulate against their ML-KEM key, run the
+    /// classical X25519 DH against their ephemeral, and derive the
+    /// session key from `HKDF-SHA3-256(dh_ss || mlkem_ss)`.
+    pub fn respond_hybrid(
+        &self,
+        init: &HybridInitMessage,
+    ) -> Result<(Session, HybridResponseMessage), SignedDHError> {
+        let init_transcript = hybrid_init_transcript(&init.x25519_pk, &init.kem_encapsulation_key);
+        if !dsa_verify(init.level, &init.identity_pk, &init_transcript, &init.signature) {
+            return Err(SignedDHError::InvalidSignature);
+        }
+
+        let (kem_ciphertext, mlkem_ss) = kem_encapsulate(init.level, &init.kem_encapsulation_key);
+        let dh_ss = shared_secret(&self.ephemeral.sk, &init.x25519_pk);
+
+        let cipher_suite = init.level.cipher_suite();
+        let response_transcript =
+            hybrid_response_transcript(&init_transcript, &self.ephemeral.pk, &kem_ciphertext);
+        let signature = self.dsa.sign(&response_transcript);
+
+        let response = HybridResponseMessage {
+            level: init.level,
+            identity_pk: self.dsa.verifying_key.clone(),
+            x25519_pk: self.ephemeral.pk,
+            kem_ciphertext,
+            signature,
+        };
+
+        let session = derive_hybrid_session(
+            &dh_ss,
+            &mlkem_ss,
+            cipher_suite.kem.as_bytes(),
+            &self.ephemeral.pk,
+            &init.x25519_pk,
+        );
+        Ok((session, response))
     }
+
+    /// Initiator side: verify the responder's ML-DSA signature, decapsulate
+    /// the ML-KEM ciphertext, and derive the same session key.
+    pub fn complete_hybrid(
+        &self,
+        init: &HybridInitMessage,
+        response: &HybridResponseMessage,
+    ) -> Result<Session, SignedDHError> {
+        let init_transcript = hybrid_init_transcript(&init.x25519_pk, &init.kem_encapsulation_key);
+        let response_transcript =
+            hybrid_response_transcript(&init_transcript, &response.x25519_pk, &response.kem_ciphertext);
+
+        if !dsa_verify(
+            response.level,
+            &response.identity_pk,
+            &response_transcript,
+            &response.signature,
+        ) {
+            return Err(SignedDHError::InvalidSignature);
+        }
+
+        let mlkem_ss = self.kem.decapsulate(&response.kem_ciphertext);
+        let dh_ss = shared_secret(&self.ephemeral.sk, &response.x25519_pk);
+
+        let cipher_suite = response.level.cipher_suite();
+        Ok(derive_hybrid_session(
+            &dh_ss,
+            &mlkem_ss,
+            cipher_suite.kem.as_bytes(),
+            &self.ephemeral.pk,
+            &response.x25519_pk,
+        ))
+    }
+}
+
+/// The initiator's message in the hybrid handshake: a classical X25519
+/// ephemeral key and an ML-KEM encapsulation key, both ML-DSA signed.
+pub struct HybridInitMessage {
+    pub level: SecurityLevel,
+    pub identity_pk: Vec<u8>,
+    pub x25519_pk: DHPublicKey,
+    pub kem_encapsulation_key: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+/// The responder's message: its own X25519 ephemeral key and the ML-KEM
+/// ciphertext encapsulated against the initiator's key, ML-DSA signed.
+pub struct HybridResponseMessage {
+    pub level: SecurityLevel,
+    pub identity_pk: Vec<u8>,
+    pub x25519_pk: DHPublicKey,
+    pub kem_ciphertext: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+fn hybrid_init_transcript(x25519_pk: &DHPublicKey, kem_encapsulation_key: &[u8]) -> Vec<u8> {
+    let mut transcript = Vec::with_capacity(32 + kem_encapsulation_key.len());
+    transcript.extend_from_slice(x25519_pk.as_bytes());
+    transcript.extend_from_slice(kem_encapsulation_key);
+    transcript
+}
+
+fn hybrid_response_transcript(
+    init_transcript: &[u8],
+    responder_x25519_pk: &DHPublicKey,
+    kem_ciphertext: &[u8],
+) -> Vec<u8> {
+    let mut transcript =
+        Vec::with_capacity(init_transcript.len() + 32 + kem_ciphertext.len());
+    transcript.extend_from_slice(init_transcript);
+    transcript.extend_from_slice(responder_x25519_pk.as_bytes());
+    transcript.extend_from_slice(kem_ciphertext);
+    transcript
+}
+
+/// Hybrid combiner: `IKM = dh_ss || mlkem_ss`, expanded with HKDF-SHA3-256
+/// under an `info` string bound to the negotiated `CipherSuite`.
+fn derive_hybrid_session(
+    dh_ss: &[u8; 32],
+    mlkem_ss: &[u8],
+    info: &[u8],
+    self_pk: &DHPublicKey,
+    peer_pk: &DHPublicKey,
+) -> Session {
+    let mut ikm = Vec::with_capacity(dh_ss.len() + mlkem_ss.len());
+    ikm.extend_from_slice(dh_ss);
+    ikm.extend_from_slice(mlkem_ss);
+
+    let key = derive_aes256gcm_key(&ikm, None, info);
+    new_session(key, *dh_ss, self_pk, peer_pk)
 }
 
 impl Default for Participant {
@@ -109,26 +498,111 @@ impl Default for Participant {
 }
 
 impl Session {
-    /// Encrypt a message with AES-256-GCM
-    pub fn encrypt(
-        &self,
-        nonce: &Nonce,
-        plaintext: &[u8],
-        aad: &[u8],
-    ) -> Result<Vec<u8>, SessionError> {
-        aead::encrypt(&self.key, nonce, plaintext, aad)
-            .map_err(|_| SessionError::EncryptionFailed)
+    /// Encrypt `plaintext` under this session's send-side nonce counter.
+    /// The wire format is `be64(sequence) || ciphertext`; the sequence
+    /// number does not need to be secret, only unique per direction. The
+    /// plaintext is first framed with a 4-byte true-length prefix and
+    /// padded per `self.padding` (see `set_padding_policy`), so ciphertext
+    /// size reflects the padding bucket rather than the exact message
+    /// length.
+    pub fn encrypt(&self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, SessionError> {
+        let sequence = self.send_sequence.get();
+        if sequence == u64::MAX {
+            return Err(SessionError::SequenceExhausted);
+        }
+
+        let framed_plaintext = Self::frame_with_padding(plaintext, self.padding.get())?;
+        let nonce = nonce_for(&self.send_salt, sequence);
+        let ciphertext = aead::encrypt(&self.key, &nonce, &framed_plaintext, aad)
+            .map_err(|_| SessionError::EncryptionFailed)?;
+        self.send_sequence.set(sequence + 1);
+
+        let mut framed = Vec::with_capacity(8 + ciphertext.len());
+        framed.extend_from_slice(&sequence.to_be_bytes());
+        framed.extend_from_slice(&ciphertext);
+        Ok(framed)
     }
 
-    /// Decrypt a message with AES-256-GCM
-    pub fn decrypt(
-        &self,
-        nonce: &Nonce,
-        ciphertext: &[u8],
-        aad: &[u8],
-    ) -> Result<Vec<u8>, SessionError> {
-        aead::decrypt(&self.key, nonce, ciphertext, aad)
-            .map_err(|_| SessionError::DecryptionFailed)
+    /// Decrypt a message produced by the peer's `encrypt`. Rejects a
+    /// sequence number that is a replay or has fallen outside the sliding
+    /// window before the AEAD tag is ever checked, and only records the
+    /// sequence as seen once authentication succeeds.
+    pub fn decrypt(&self, framed: &[u8], aad: &[u8]) -> Result<Vec<u8>, SessionError> {
+        if framed.len() < 8 {
+            return Err(SessionError::DecryptionFailed);
+        }
+        let (sequence_bytes, ciphertext) = framed.split_at(8);
+        let sequence = u64::from_be_bytes(
+            sequence_bytes.try_into().expect("split_at(8) yields an 8-byte slice"),
+        );
+
+        if !self.recv_window.borrow().check(sequence) {
+            return Err(SessionError::ReplayedMessage);
+        }
+
+        let nonce = nonce_for(&self.recv_salt, sequence);
+        let framed_plaintext = aead::decrypt(&self.key, &nonce, ciphertext, aad)
+            .map_err(|_| SessionError::DecryptionFailed)?;
+
+        self.recv_window.borrow_mut().record(sequence);
+        Self::unframe(&framed_plaintext)
+    }
+
+    /// Prepend `plaintext`'s true length (big-endian `u32`) and pad the
+    /// result out to `policy`'s target size with zero bytes.
+    fn frame_with_padding(plaintext: &[u8], policy: PaddingPolicy) -> Result<Vec<u8>, SessionError> {
+        let padded_len = policy.padded_len(4 + plaintext.len())?;
+        let mut framed = Vec::with_capacity(padded_len);
+        framed.extend_from_slice(&(plaintext.len() as u32).to_be_bytes());
+        framed.extend_from_slice(plaintext);
+        framed.resize(padded_len, 0);
+        Ok(framed)
+    }
+
+    /// Inverse of `frame_with_padding`: read the true-length prefix and
+    /// truncate away the padding, rejecting a declared length that doesn't
+    /// fit in what was actually decrypted.
+    fn unframe(framed: &[u8]) -> Result<Vec<u8>, SessionError> {
+        if framed.len() < 4 {
+            return Err(SessionError::DecryptionFailed);
+        }
+        let (len_bytes, rest) = framed.split_at(4);
+        let true_len = u32::from_be_bytes(
+            len_bytes.try_into().expect("split_at(4) yields a 4-byte slice"),
+        ) as usize;
+
+        if true_len > rest.len() {
+            return Err(SessionError::InvalidPadding);
+        }
+        Ok(rest[..true_len].to_vec())
+    }
+
+    /// Reconfigure how many trailing sequence numbers the receive side
+    /// will accept out of order. Only meaningful before the first `decrypt`.
+    pub fn set_replay_window(&mut self, window: u64) {
+        self.recv_window = RefCell::new(ReplayWindow::with_window(window));
+    }
+
+    /// Select how future `encrypt` calls hide the true message length.
+    /// Takes effect on the next `encrypt`; already-sent messages are
+    /// unaffected.
+    pub fn set_padding_policy(&self, policy: PaddingPolicy) {
+        self.padding.set(policy);
+    }
+
+    /// Encrypt `plaintext` for at-rest storage (e.g. a local database row)
+    /// with AES-256-GCM-SIV under this session's key, using the caller's
+    /// own `nonce` rather than the internal send-sequence counter. Unlike
+    /// `encrypt`, callers don't need a fresh nonce per call: SIV's
+    /// synthetic IV means a reused nonce only reveals plaintext equality,
+    /// not the key or keystream.
+    pub fn seal_stored(&self, nonce: &Nonce, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, SessionError> {
+        aead::encrypt_siv(&self.key, nonce, plaintext, aad).map_err(|_| SessionError::EncryptionFailed)
+    }
+
+    /// Decrypt a blob produced by `seal_stored`.
+    pub fn open_stored(&self, nonce: &Nonce, ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>, SessionError> {
+        aead::decrypt_siv(&self.key, nonce, ciphertext, aad).map_err(|_| SessionError::DecryptionFailed)
     }
 }
 
@@ -156,6 +630,15 @@ impl std::error::Error for SignedDHError {}
 pub enum SessionError {
     EncryptionFailed,
     DecryptionFailed,
+    /// The sequence number was a replay, or older than the receive window
+    ReplayedMessage,
+    /// The 64-bit nonce counter is exhausted; the session must be rekeyed
+    SequenceExhausted,
+    /// The padded message would exceed `PADDED_MAX_SIZE`
+    PaddedMessageTooLarge,
+    /// The decrypted length prefix declared more bytes than were actually
+    /// decrypted
+    InvalidPadding,
 }
 
 impl std::fmt::Display for SessionError {
@@ -163,6 +646,10 @@ impl std::fmt::Display for SessionError {
         match self {
             SessionError::EncryptionFailed => write!(f, "Encryption failed"),
             SessionError::DecryptionFailed => write!(f, "Decryption failed (auth tag mismatch)"),
+            SessionError::ReplayedMessage => write!(f, "Rejected replayed or out-of-window sequence number"),
+            SessionError::SequenceExhausted => write!(f, "Session nonce counter exhausted; rekey required"),
+            SessionError::PaddedMessageTooLarge => write!(f, "Padded message exceeds PADDED_MAX_SIZE"),
+            SessionError::InvalidPadding => write!(f, "Declared plaintext length exceeds the decrypted buffer"),
         }
     }
 }
@@ -172,7 +659,6 @@ impl std::error::Error for SessionError {}
 #[cfg(test)]
 mod tests {
     use super::*;
-    use rand::{rngs::OsRng, RngCore};
 
     #[test]
     fn test_signed_dh_protocol() {
@@ -194,17 +680,120 @@ mod tests {
         assert_eq!(alice_session.shared_secret, bob_session.shared_secret);
 
         // Test encryption/decryption
-        let mut nonce = [0u8; 12];
-        OsRng.fill_bytes(&mut nonce);
         let plaintext = b"Hello, signed DH!";
         let aad = b"additional data";
 
-        let ciphertext = alice_session.encrypt(&nonce, plaintext, aad).unwrap();
-        let decrypted = bob_session.decrypt(&nonce, &ciphertext, aad).unwrap();
+        let ciphertext = alice_session.encrypt(plaintext, aad).unwrap();
+        let decrypted = bob_session.decrypt(&ciphertext, aad).unwrap();
 
         assert_eq!(plaintext.as_slice(), decrypted.as_slice());
     }
 
+    #[test]
+    fn test_replayed_message_rejected() {
+        let alice = Participant::new();
+        let bob = Participant::new();
+
+        let alice_msg = alice.key_exchange_message();
+        let bob_msg = bob.key_exchange_message();
+
+        let alice_session = alice.establish_session(&bob_msg, b"replay-test").unwrap();
+        let bob_session = bob.establish_session(&alice_msg, b"replay-test").unwrap();
+
+        let ciphertext = alice_session.encrypt(b"hi bob", b"").unwrap();
+        assert!(bob_session.decrypt(&ciphertext, b"").is_ok());
+
+        // Replaying the exact same record must be rejected even though
+        // the AEAD tag is still valid.
+        let result = bob_session.decrypt(&ciphertext, b"");
+        assert!(matches!(result, Err(SessionError::ReplayedMessage)));
+    }
+
+    #[test]
+    fn test_3dh_session_establishment() {
+        let alice = Participant::new();
+        let bob = Participant::new();
+
+        let alice_msg = alice.three_dh_message();
+        let bob_msg = bob.three_dh_message();
+
+        let info = b"test_3dh";
+        let alice_session = alice.establish_session_3dh(&bob_msg, info);
+        let bob_session = bob.establish_session_3dh(&alice_msg, info);
+
+        assert_eq!(alice_session.key, bob_session.key);
+    }
+
+    #[test]
+    fn test_3dh_disagrees_on_wrong_static_key() {
+        let alice = Participant::new();
+        let bob = Participant::new();
+        let mallory = Participant::new();
+
+        // Alice thinks she's talking to Bob, but Bob actually has a
+        // different static key than the one Alice uses (e.g. Mallory's).
+        let mut bob_msg = bob.three_dh_message();
+        bob_msg.static_pk = mallory.three_dh_message().static_pk;
+
+        let info = b"test_3dh_mismatch";
+        let alice_session = alice.establish_session_3dh(&bob_msg, info);
+        let bob_session = bob.establish_session_3dh(&alice.three_dh_message(), info);
+
+        // No signature to reject up front; the keys simply disagree.
+        assert_ne!(alice_session.key, bob_session.key);
+    }
+
+    #[test]
+    fn test_fixed_bucket_padding_hides_true_length() {
+        let alice = Participant::new();
+        let bob = Participant::new();
+
+        let alice_msg = alice.key_exchange_message();
+        let bob_msg = bob.key_exchange_message();
+
+        let alice_session = alice.establish_session(&bob_msg, b"padding-test").unwrap();
+        let bob_session = bob.establish_session(&alice_msg, b"padding-test").unwrap();
+
+        alice_session.set_padding_policy(PaddingPolicy::FixedBucket(64));
+
+        let short = alice_session.encrypt(b"hi", b"").unwrap();
+        let longer = alice_session.encrypt(b"a slightly longer message", b"").unwrap();
+
+        // Both land in the same padding bucket, so the framed messages
+        // (8-byte sequence prefix + ciphertext) come out the same length.
+        assert_eq!(short.len(), longer.len());
+
+        assert_eq!(bob_session.decrypt(&short, b"").unwrap(), b"hi");
+        assert_eq!(bob_session.decrypt(&longer, b"").unwrap(), b"a slightly longer message");
+    }
+
+    #[test]
+    fn test_seal_stored_round_trips_and_is_deterministic_under_nonce_reuse() {
+        let alice = Participant::new();
+        let bob = Participant::new();
+
+        let bob_msg = bob.key_exchange_message();
+        let session = alice.establish_session(&bob_msg, b"stored-test").unwrap();
+
+        let reused_nonce = [7u8; 12];
+        let sealed = session.seal_stored(&reused_nonce, b"row one", b"table:users").unwrap();
+        let opened = session.open_stored(&reused_nonce, &sealed, b"table:users").unwrap();
+        assert_eq!(opened, b"row one");
+
+        // Re-sealing the exact same plaintext/AAD under a reused nonce
+        // produces the exact same ciphertext (the point of a synthetic
+        // IV) - it leaks that the two rows are equal, nothing more.
+        let sealed_again = session.seal_stored(&reused_nonce, b"row one", b"table:users").unwrap();
+        assert_eq!(sealed, sealed_again);
+
+        // A different plaintext under the same reused nonce still
+        // authenticates and decrypts correctly.
+        let sealed_other = session.seal_stored(&reused_nonce, b"row two", b"table:users").unwrap();
+        let opened_other = session.open_stored(&reused_nonce, &sealed_other, b"table:users").unwrap();
+        assert_eq!(opened_other, b"row two");
+        assert_ne!(sealed, sealed_other);
+    }
+
     #[test]
     fn test_invalid_signature_rejected() {
         let alice = Participant::new();
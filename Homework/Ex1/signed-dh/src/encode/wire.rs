@@ -0,0 +1,119 @@
+//! Versioned binary encoding for handshake messages and encrypted
+//! envelopes, for applications that want to send them over a socket
+//! instead of printing base64 to a terminal.
+//!
+//! Every encoding starts with a format/version byte and a `CipherSuite`
+//! tag, followed by length-prefixed fields.
+
+use crate::crypto::params::CipherSuite;
+use crate::crypto::signed_dh::{EncryptedEnvelope, KeyExchangeMessage};
+
+const FORMAT_VERSION: u8 = 1;
+
+/// Errors decoding a wire-format buffer.
+#[derive(Debug, Clone, Copy)]
+pub enum WireError {
+    /// The buffer ended before a length-prefixed field could be read
+    Truncated,
+    /// The format/version byte was not one this crate understands
+    UnsupportedVersion(u8),
+    /// The cipher suite tag did not match a known `CipherSuite`
+    UnknownCipherSuite(u8),
+    /// A fixed-size field had the wrong length, or the identity key field
+    /// was not a valid Ed25519 public key
+    BadFieldLength,
+}
+
+impl std::fmt::Display for WireError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WireError::Truncated => write!(f, "buffer ended before a length-prefixed field"),
+            WireError::UnsupportedVersion(v) => write!(f, "unsupported wire format version {v}"),
+            WireError::UnknownCipherSuite(t) => write!(f, "unknown cipher suite tag {t}"),
+            WireError::BadFieldLength => write!(f, "field had an unexpected length"),
+        }
+    }
+}
+
+impl std::error::Error for WireError {}
+
+fn push_field(out: &mut Vec<u8>, field: &[u8]) {
+    out.extend_from_slice(&(field.len() as u32).to_be_bytes());
+    out.extend_from_slice(field);
+}
+
+fn read_field<'a>(buf: &'a [u8], pos: &mut usize) -> Result<&'a [u8], WireError> {
+    let len_bytes = buf.get(*pos..*pos + 4).ok_or(WireError::Truncated)?;
+    let len = u32::from_be_bytes(len_bytes.try_into().expect("4 bytes")) as usize;
+    *pos += 4;
+
+    let field = buf.get(*pos..*pos + len).ok_or(WireError::Truncated)?;
+    *pos += len;
+    Ok(field)
+}
+
+/// Encode a `KeyExchangeMessage` as `version || suite_tag || fields...`.
+pub fn key_exchange_message_to_bytes(msg: &KeyExchangeMessage, suite: CipherSuite) -> Vec<u8> {
+    let mut out = vec![FORMAT_VERSION, suite.tag()];
+    push_field(&mut out, &msg.identity_pk.to_bytes());
+    push_field(&mut out, msg.ephemeral_pk.as_bytes());
+    push_field(&mut out, &msg.signature.to_bytes());
+    out
+}
+
+/// Decode a `KeyExchangeMessage` produced by `key_exchange_message_to_bytes`.
+pub fn key_exchange_message_from_bytes(
+    buf: &[u8],
+) -> Result<(KeyExchangeMessage, CipherSuite), WireError> {
+    let [version, suite_tag] = *buf.get(0..2).ok_or(WireError::Truncated)? else {
+        unreachable!("slice pattern of length 2")
+    };
+    if version != FORMAT_VERSION {
+        return Err(WireError::UnsupportedVersion(version));
+    }
+    let suite = CipherSuite::from_tag(suite_tag).ok_or(WireError::UnknownCipherSuite(suite_tag))?;
+
+    let mut pos = 2;
+    let identity_pk: [u8; 32] = read_field(buf, &mut pos)?
+        .try_into()
+        .map_err(|_| WireError::BadFieldLength)?;
+    let ephemeral_pk: [u8; 32] = read_field(buf, &mut pos)?
+        .try_into()
+        .map_err(|_| WireError::BadFieldLength)?;
+    let signature: [u8; 64] = read_field(buf, &mut pos)?
+        .try_into()
+        .map_err(|_| WireError::BadFieldLength)?;
+
+    let msg = KeyExchangeMessage {
+        identity_pk: ed25519_dalek::VerifyingKey::from_bytes(&identity_pk)
+            .map_err(|_| WireError::BadFieldLength)?,
+        ephemeral_pk: x25519_dalek::PublicKey::from(ephemeral_pk),
+        signature: ed25519_dalek::Signature::from_bytes(&signature),
+    };
+    Ok((msg, suite))
+}
+
+/// Encode an `EncryptedEnvelope` as `version || suite_tag || fields...`.
+pub fn envelope_to_bytes(envelope: &EncryptedEnvelope, suite: CipherSuite) -> Vec<u8> {
+    let mut out = vec![FORMAT_VERSION, suite.tag()];
+    push_field(&mut out, &envelope.ciphertext);
+    push_field(&mut out, &envelope.aad);
+    out
+}
+
+/// Decode an `EncryptedEnvelope` produced by `envelope_to_bytes`.
+pub fn envelope_from_bytes(buf: &[u8]) -> Result<(EncryptedEnvelope, CipherSuite), WireError> {
+    let [version, suite_tag] = *buf.get(0..2).ok_or(WireError::Truncated)? else {
+        unreachable!("slice pattern of length 2")
+    };
+    if version != FORMAT_VERSION {
+        return Err(WireError::UnsupportedVersion(version));
+    }
+    let suite = CipherSuite::from_tag(suite_tag).ok_or(WireError::UnknownCipherSuite(suite_tag))?;
+
+    let mut pos = 2;
+    let ciphertext = read_field(buf, &mut pos)?.to_vec();
+    let aad = read_field(buf, &mut pos)?.to_vec();
+
+    Ok((EncryptedEnvelope::new(ciphertext, aad), suite))
+}
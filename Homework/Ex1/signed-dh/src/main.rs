@@ -3,7 +3,6 @@ mod io;
 mod encode;
 
 use anyhow::Result;
-use rand::{rngs::OsRng, RngCore};
 
 use crypto::signed_dh::Participant;
 use encode::encode_b64::b64;
@@ -129,19 +128,14 @@ fn main() -> Result<()> {
             break;
         }
 
-        // Generate fresh nonce
-        let mut nonce = [0u8; 12];
-        OsRng.fill_bytes(&mut nonce);
-
-        // Alice encrypts
-        match alice_session.encrypt(&nonce, msg.as_bytes(), aad.as_bytes()) {
+        // Alice encrypts (nonce is derived internally from her send sequence)
+        match alice_session.encrypt(msg.as_bytes(), aad.as_bytes()) {
             Ok(ct) => {
                 println!("\n[Alice -> Bob]");
-                println!("  Nonce:      {}", b64(&nonce));
                 println!("  Ciphertext: {}", b64(&ct));
 
                 // Bob decrypts
-                match bob_session.decrypt(&nonce, &ct, aad.as_bytes()) {
+                match bob_session.decrypt(&ct, aad.as_bytes()) {
                     Ok(pt) => {
                         println!("  Bob decrypted: '{}'\n", String::from_utf8_lossy(&pt));
                     }
@@ -162,18 +156,14 @@ fn main() -> Result<()> {
             break;
         }
 
-        // Fresh nonce for reply
-        OsRng.fill_bytes(&mut nonce);
-
         // Bob encrypts
-        match bob_session.encrypt(&nonce, reply.as_bytes(), aad.as_bytes()) {
+        match bob_session.encrypt(reply.as_bytes(), aad.as_bytes()) {
             Ok(ct) => {
                 println!("\n[Bob -> Alice]");
-                println!("  Nonce:      {}", b64(&nonce));
                 println!("  Ciphertext: {}", b64(&ct));
 
                 // Alice decrypts
-                match alice_session.decrypt(&nonce, &ct, aad.as_bytes()) {
+                match alice_session.decrypt(&ct, aad.as_bytes()) {
                     Ok(pt) => {
                         println!("  Alice decrypted: '{}'\n", String::from_utf8_lossy(&pt));
                     }
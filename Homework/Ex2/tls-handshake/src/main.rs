@@ -1,4 +1,4 @@
-use sha2::{Sha256, Digest};
+use sha2::{Sha256, Sha384, Digest};
 use hkdf::Hkdf;
 use hmac::{Hmac, Mac};
 use p256::{
@@ -8,265 +8,604 @@ use p256::{
     elliptic_curve::sec1::{ToEncodedPoint, FromEncodedPoint},
 };
 use rand::rngs::OsRng;
+use aead::{Aead, KeyInit};
+use aes_gcm::{Aes128Gcm, Aes256Gcm, Nonce as AesNonce};
+use chacha20poly1305::ChaCha20Poly1305;
 
 type HmacSha256 = Hmac<Sha256>;
+type HmacSha384 = Hmac<Sha384>;
+
+/// Hash algorithm negotiated for a handshake's key schedule. The suite
+/// isn't known until ClientHello/ServerHello negotiate it, so this
+/// dispatches at runtime via the enum rather than through monomorphized
+/// generics over `Digest`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HashAlgorithm {
+    Sha256,
+    Sha384,
+}
+
+impl HashAlgorithm {
+    fn hash_len(self) -> usize {
+        match self {
+            HashAlgorithm::Sha256 => 32,
+            HashAlgorithm::Sha384 => 48,
+        }
+    }
+
+    fn hash(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            HashAlgorithm::Sha256 => Sha256::digest(data).to_vec(),
+            HashAlgorithm::Sha384 => Sha384::digest(data).to_vec(),
+        }
+    }
+
+    fn extract(self, salt: &[u8], ikm: &[u8]) -> Vec<u8> {
+        match self {
+            HashAlgorithm::Sha256 => Hkdf::<Sha256>::extract(Some(salt), ikm).0.to_vec(),
+            HashAlgorithm::Sha384 => Hkdf::<Sha384>::extract(Some(salt), ikm).0.to_vec(),
+        }
+    }
+
+    fn expand(self, prk: &[u8], info: &[u8], len: usize) -> Vec<u8> {
+        let mut out = vec![0u8; len];
+        match self {
+            HashAlgorithm::Sha256 => Hkdf::<Sha256>::from_prk(prk)
+                .expect("Invalid PRK")
+                .expand(info, &mut out)
+                .expect("Expand failed"),
+            HashAlgorithm::Sha384 => Hkdf::<Sha384>::from_prk(prk)
+                .expect("Invalid PRK")
+                .expand(info, &mut out)
+                .expect("Expand failed"),
+        }
+        out
+    }
+
+    fn hmac(self, key: &[u8], data: &[u8]) -> Vec<u8> {
+        match self {
+            HashAlgorithm::Sha256 => {
+                let mut mac = HmacSha256::new_from_slice(key).expect("HMAC can take key of any size");
+                mac.update(data);
+                mac.finalize().into_bytes().to_vec()
+            }
+            HashAlgorithm::Sha384 => {
+                let mut mac = HmacSha384::new_from_slice(key).expect("HMAC can take key of any size");
+                mac.update(data);
+                mac.finalize().into_bytes().to_vec()
+            }
+        }
+    }
+
+    fn verify_hmac(self, key: &[u8], data: &[u8], expected: &[u8]) -> bool {
+        match self {
+            HashAlgorithm::Sha256 => {
+                let mut mac = HmacSha256::new_from_slice(key).expect("HMAC can take key of any size");
+                mac.update(data);
+                mac.verify_slice(expected).is_ok()
+            }
+            HashAlgorithm::Sha384 => {
+                let mut mac = HmacSha384::new_from_slice(key).expect("HMAC can take key of any size");
+                mac.update(data);
+                mac.verify_slice(expected).is_ok()
+            }
+        }
+    }
+}
+
+/// AEAD algorithm negotiated for the record layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AeadAlgorithm {
+    Aes128Gcm,
+    Aes256Gcm,
+    ChaCha20Poly1305,
+}
+
+impl AeadAlgorithm {
+    fn key_len(self) -> usize {
+        match self {
+            AeadAlgorithm::Aes128Gcm => 16,
+            AeadAlgorithm::Aes256Gcm => 32,
+            AeadAlgorithm::ChaCha20Poly1305 => 32,
+        }
+    }
+
+    fn encrypt(self, key: &[u8], nonce: &[u8; 12], plaintext: &[u8]) -> Vec<u8> {
+        let nonce = AesNonce::from_slice(nonce);
+        match self {
+            AeadAlgorithm::Aes128Gcm => Aes128Gcm::new_from_slice(key)
+                .expect("correct key length")
+                .encrypt(nonce, plaintext)
+                .expect("record encryption failed"),
+            AeadAlgorithm::Aes256Gcm => Aes256Gcm::new_from_slice(key)
+                .expect("correct key length")
+                .encrypt(nonce, plaintext)
+                .expect("record encryption failed"),
+            AeadAlgorithm::ChaCha20Poly1305 => ChaCha20Poly1305::new_from_slice(key)
+                .expect("correct key length")
+                .encrypt(nonce, plaintext)
+                .expect("record encryption failed"),
+        }
+    }
+
+    fn decrypt(self, key: &[u8], nonce: &[u8; 12], ciphertext: &[u8]) -> Result<Vec<u8>, aead::Error> {
+        let nonce = AesNonce::from_slice(nonce);
+        match self {
+            AeadAlgorithm::Aes128Gcm => Aes128Gcm::new_from_slice(key)
+                .expect("correct key length")
+                .decrypt(nonce, ciphertext),
+            AeadAlgorithm::Aes256Gcm => Aes256Gcm::new_from_slice(key)
+                .expect("correct key length")
+                .decrypt(nonce, ciphertext),
+            AeadAlgorithm::ChaCha20Poly1305 => ChaCha20Poly1305::new_from_slice(key)
+                .expect("correct key length")
+                .decrypt(nonce, ciphertext),
+        }
+    }
+}
+
+/// A negotiable cipher suite: a (hash, AEAD) pair, the way TLS 1.3 bundles
+/// them. `ClientHello` offers a list, `ServerHello` selects one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CipherSuite {
+    Aes128GcmSha256,
+    Aes256GcmSha384,
+    ChaCha20Poly1305Sha256,
+}
+
+impl CipherSuite {
+    const ALL: [CipherSuite; 3] = [
+        CipherSuite::Aes128GcmSha256,
+        CipherSuite::Aes256GcmSha384,
+        CipherSuite::ChaCha20Poly1305Sha256,
+    ];
+
+    fn hash(self) -> HashAlgorithm {
+        match self {
+            CipherSuite::Aes128GcmSha256 => HashAlgorithm::Sha256,
+            CipherSuite::Aes256GcmSha384 => HashAlgorithm::Sha384,
+            CipherSuite::ChaCha20Poly1305Sha256 => HashAlgorithm::Sha256,
+        }
+    }
+
+    fn aead(self) -> AeadAlgorithm {
+        match self {
+            CipherSuite::Aes128GcmSha256 => AeadAlgorithm::Aes128Gcm,
+            CipherSuite::Aes256GcmSha384 => AeadAlgorithm::Aes256Gcm,
+            CipherSuite::ChaCha20Poly1305Sha256 => AeadAlgorithm::ChaCha20Poly1305,
+        }
+    }
+
+    /// A single byte identifying the suite, fed into the transcript so the
+    /// negotiation itself is bound to the derived keys (a downgrade to a
+    /// weaker suite changes the transcript hash).
+    fn id(self) -> u8 {
+        match self {
+            CipherSuite::Aes128GcmSha256 => 0,
+            CipherSuite::Aes256GcmSha384 => 1,
+            CipherSuite::ChaCha20Poly1305Sha256 => 2,
+        }
+    }
+
+    /// This demo server supports every suite, so it always honors the
+    /// client's top preference.
+    fn select(offered: &[CipherSuite]) -> CipherSuite {
+        *offered.first().expect("ClientHello must offer at least one suite")
+    }
+}
+
+/// Running transcript hash over every handshake message as it is produced
+/// or consumed. Feeding `current_hash()` into `hkdf_expand_label` instead of
+/// re-concatenating raw fields at each step binds every derived secret to
+/// the entire handshake seen so far. Buffers the raw transcript rather than
+/// streaming it through a live digest so the same transcript can be hashed
+/// under whichever algorithm the negotiated cipher suite selects; each
+/// `current_hash()` call rehashes the whole buffer.
+struct Transcript {
+    hash: HashAlgorithm,
+    messages: Vec<u8>,
+}
+
+impl Transcript {
+    fn new(hash: HashAlgorithm) -> Self {
+        Transcript { hash, messages: Vec::new() }
+    }
+
+    fn update(&mut self, message: &[u8]) {
+        self.messages.extend_from_slice(message);
+    }
+
+    fn current_hash(&self) -> Vec<u8> {
+        self.hash.hash(&self.messages)
+    }
+}
+
+/// HKDF-Expand-Label: builds the structured info block
+/// `u16(len) || u8(len("crypto " + label)) || "crypto " || label || u8(len(context)) || context`
+/// before calling `Hkdf::expand`, per the TLS 1.3 `HKDF-Expand-Label` construction.
+fn hkdf_expand_label(hash: HashAlgorithm, prk: &[u8], label: &[u8], context: &[u8], len: usize) -> Vec<u8> {
+    let full_label_len = b"crypto ".len() + label.len();
+    let mut info = Vec::with_capacity(2 + 1 + full_label_len + 1 + context.len());
+    info.extend_from_slice(&(len as u16).to_be_bytes());
+    info.push(full_label_len as u8);
+    info.extend_from_slice(b"crypto ");
+    info.extend_from_slice(label);
+    info.push(context.len() as u8);
+    info.extend_from_slice(context);
+
+    hash.expand(prk, &info, len)
+}
 
 // Key Schedule Functions
 
 /// DeriveHS(g^xy): Derives the handshake secret from the shared DH secret
-/// DeriveHS(g^xy): Derives the handshake secret from the shared DH secret
-fn derive_hs(shared_secret: &[u8]) -> [u8; 32] {
+fn derive_hs(hash: HashAlgorithm, shared_secret: &[u8]) -> Vec<u8> {
     // 1. ES = HKDF.Extract(0, 0) - extract with zero salt and zero IKM
-    let zeros = [0u8; 32];
-    let (es_prk, hkdf_es) = Hkdf::<Sha256>::extract(Some(&zeros), &zeros);
-    
-    // 2. dES = HKDF.Expand(ES, SHA256("DerivedES"))
-    let mut derived_es = [0u8; 32];
-    let mut hasher = Sha256::new();
-    hasher.update(b"DerivedES");
-    let des_info = hasher.finalize();
-    hkdf_es.expand(&des_info, &mut derived_es).expect("Expand failed");
-    
-    // 3. HS = HKDF.Extract(dES, SHA256(g^xy))
-    let mut hasher = Sha256::new();
-    hasher.update(shared_secret);
-    let gxy_hash = hasher.finalize();
-    
-    let (hs_prk, _hkdf_hs) = Hkdf::<Sha256>::extract(Some(&derived_es), &gxy_hash);
-    let mut hs = [0u8; 32];
-    hs.copy_from_slice(&hs_prk);
-    
-    hs
+    let zeros = vec![0u8; hash.hash_len()];
+    let es = hash.extract(&zeros, &zeros);
+
+    // 2. dES = HKDF-Expand-Label(ES, "derived es", "", hash_len)
+    let derived_es = hkdf_expand_label(hash, &es, b"derived es", &[], hash.hash_len());
+
+    // 3. HS = HKDF.Extract(dES, Hash(g^xy))
+    let gxy_hash = hash.hash(shared_secret);
+    hash.extract(&derived_es, &gxy_hash)
 }
 
 /// KeySchedule1(g^xy): First key schedule
-fn key_schedule_1(shared_secret: &[u8]) -> ([u8; 32], [u8; 32]) {
-    // 1. HS = DeriveHS(g^xy)
-    let hs = derive_hs(shared_secret);
-    
-    // Create HKDF from HS for expansion
-    let hkdf = Hkdf::<Sha256>::from_prk(&hs).expect("Invalid PRK");
-    
-    // 2. K1C = HKDF.Expand(HS, SHA256("ClientKE"))
-    let mut hasher = Sha256::new();
-    hasher.update(b"ClientKE");
-    let client_info = hasher.finalize();
-    
-    let mut k1c = [0u8; 32];
-    hkdf.expand(&client_info, &mut k1c).expect("Expand failed");
-    
-    // 3. K1S = HKDF.Expand(HS, SHA256("ServerKE"))
-    let mut hasher = Sha256::new();
-    hasher.update(b"ServerKE");
-    let server_info = hasher.finalize();
-    
-    let mut k1s = [0u8; 32];
-    hkdf.expand(&server_info, &mut k1s).expect("Expand failed");
-    
+fn key_schedule_1(hash: HashAlgorithm, shared_secret: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    key_schedule_1_from_hs(hash, &derive_hs(hash, shared_secret))
+}
+
+fn key_schedule_1_from_hs(hash: HashAlgorithm, hs: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    // K1C = HKDF-Expand-Label(HS, "client ke", "", hash_len)
+    let k1c = hkdf_expand_label(hash, hs, b"client ke", &[], hash.hash_len());
+
+    // K1S = HKDF-Expand-Label(HS, "server ke", "", hash_len)
+    let k1s = hkdf_expand_label(hash, hs, b"server ke", &[], hash.hash_len());
+
     (k1c, k1s)
 }
 
-/// KeySchedule2: Second key schedule with nonces and public keys
-fn key_schedule_2(
-    nonce_c: &[u8],
-    x: &[u8],
-    nonce_s: &[u8],
-    y: &[u8],
-    shared_secret: &[u8],
-) -> ([u8; 32], [u8; 32]) {
-    // 1. HS = DeriveHS(g^xy)
-    let hs = derive_hs(shared_secret);
-    let hkdf = Hkdf::<Sha256>::from_prk(&hs).expect("Invalid PRK");
-    
-    // 2. ClientKC = SHA256(nonceC || X || nonceS || Y || "ClientKC")
-    let mut hasher = Sha256::new();
-    hasher.update(nonce_c);
-    hasher.update(x);
-    hasher.update(nonce_s);
-    hasher.update(y);
-    hasher.update(b"ClientKC");
-    let client_kc = hasher.finalize();
-    
-    // 3. ServerKC = SHA256(nonceC || X || nonceS || Y || "ServerKC")
-    let mut hasher = Sha256::new();
-    hasher.update(nonce_c);
-    hasher.update(x);
-    hasher.update(nonce_s);
-    hasher.update(y);
-    hasher.update(b"ServerKC");
-    let server_kc = hasher.finalize();
-    
-    // 4. K2C = HKDF.Expand(HS, ClientKC)
-    let mut k2c = [0u8; 32];
-    hkdf.expand(&client_kc, &mut k2c).expect("Expand failed");
-    
-    // 5. K2S = HKDF.Expand(HS, ServerKC)
-    let mut k2s = [0u8; 32];
-    hkdf.expand(&server_kc, &mut k2s).expect("Expand failed");
-    
+/// KeySchedule2: second key schedule, bound to the transcript hash of
+/// ClientHello || ServerHello's key-exchange fields
+fn key_schedule_2(hash: HashAlgorithm, transcript_hash: &[u8], shared_secret: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    key_schedule_2_from_hs(hash, &derive_hs(hash, shared_secret), transcript_hash)
+}
+
+fn key_schedule_2_from_hs(hash: HashAlgorithm, hs: &[u8], transcript_hash: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    // K2C = HKDF-Expand-Label(HS, "client kc", transcript_hash, hash_len)
+    let k2c = hkdf_expand_label(hash, hs, b"client kc", transcript_hash, hash.hash_len());
+
+    // K2S = HKDF-Expand-Label(HS, "server kc", transcript_hash, hash_len)
+    let k2s = hkdf_expand_label(hash, hs, b"server kc", transcript_hash, hash.hash_len());
+
     (k2c, k2s)
 }
 
-/// KeySchedule3: Third key schedule for application keys
-fn key_schedule_3(
-    nonce_c: &[u8],
-    x: &[u8],
-    nonce_s: &[u8],
-    y: &[u8],
-    shared_secret: &[u8],
-    sigma: &[u8],
-    cert_pks: &[u8],
-    mac_s: &[u8],
-) -> ([u8; 32], [u8; 32]) {
-    // 1. HS = DeriveHS(g^xy)
-    let hs = derive_hs(shared_secret);
-    let hkdf_hs = Hkdf::<Sha256>::from_prk(&hs).expect("Invalid PRK");
-    
-    // 2. dHS = HKDF.Expand(HS, SHA256("DerivedHS"))
-    let mut hasher = Sha256::new();
-    hasher.update(b"DerivedHS");
-    let dhs_info = hasher.finalize();
-    
-    let mut derived_hs = [0u8; 32];
-    hkdf_hs.expand(&dhs_info, &mut derived_hs).expect("Expand failed");
-    
-    // 3. MS = HKDF.Extract(dHS, 0)
-    let zeros = [0u8; 32];
-    let (_prk, _hkdf_ms) = Hkdf::<Sha256>::extract(Some(&derived_hs), &zeros);
-    let ms = _prk;
-    let hkdf_ms = Hkdf::<Sha256>::from_prk(&ms).expect("Invalid PRK");
-    
-    // 4. ClientSKH = SHA256(nonceC || X || nonceS || Y || σ || cert_pkS || macS || "ClientEncK")
-    let mut hasher = Sha256::new();
-    hasher.update(nonce_c);
-    hasher.update(x);
-    hasher.update(nonce_s);
-    hasher.update(y);
-    hasher.update(sigma);
-    hasher.update(cert_pks);
-    hasher.update(mac_s);
-    hasher.update(b"ClientEncK");
-    let client_skh = hasher.finalize();
-    
-    // 5. ServerSKH = SHA256(nonceC || X || nonceS || Y || σ || cert_pkS || macS || "ServerEncK")
-    let mut hasher = Sha256::new();
-    hasher.update(nonce_c);
-    hasher.update(x);
-    hasher.update(nonce_s);
-    hasher.update(y);
-    hasher.update(sigma);
-    hasher.update(cert_pks);
-    hasher.update(mac_s);
-    hasher.update(b"ServerEncK");
-    let server_skh = hasher.finalize();
-    
-    // 6. K3C = HKDF.Expand(MS, ClientSKH)
-    let mut k3c = [0u8; 32];
-    hkdf_ms.expand(&client_skh, &mut k3c).expect("Expand failed");
-    
-    // 7. K3S = HKDF.Expand(MS, ServerSKH)
-    let mut k3s = [0u8; 32];
-    hkdf_ms.expand(&server_skh, &mut k3s).expect("Expand failed");
-    
+/// Derives the Master Secret from the Handshake Secret: dHS, then
+/// MS = HKDF.Extract(dHS, 0).
+fn derive_master_secret_from_hs(hash: HashAlgorithm, hs: &[u8]) -> Vec<u8> {
+    let derived_hs = hkdf_expand_label(hash, hs, b"derived", &[], hash.hash_len());
+    let zeros = vec![0u8; hash.hash_len()];
+    hash.extract(&derived_hs, &zeros)
+}
+
+/// KeySchedule3: third key schedule for application keys, bound to the
+/// transcript hash of the full handshake up to and including ServerFinished
+/// and, in client-auth mode, the client certificate
+fn key_schedule_3(hash: HashAlgorithm, transcript_hash: &[u8], shared_secret: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    key_schedule_3_from_hs(hash, &derive_hs(hash, shared_secret), transcript_hash)
+}
+
+fn key_schedule_3_from_hs(hash: HashAlgorithm, hs: &[u8], transcript_hash: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let ms = derive_master_secret_from_hs(hash, hs);
+
+    // K3C = HKDF-Expand-Label(MS, "client ap traffic", transcript_hash, hash_len)
+    let k3c = hkdf_expand_label(hash, &ms, b"client ap traffic", transcript_hash, hash.hash_len());
+
+    // K3S = HKDF-Expand-Label(MS, "server ap traffic", transcript_hash, hash_len)
+    let k3s = hkdf_expand_label(hash, &ms, b"server ap traffic", transcript_hash, hash.hash_len());
+
     (k3c, k3s)
 }
 
-/// Compute server signature: σ = Sign(skS, SHA256(nonceC || X || nonceS || Y || cert_pkS))
-fn compute_server_signature(
-    signing_key: &SigningKey,
-    nonce_c: &[u8],
-    x: &[u8],
-    nonce_s: &[u8],
-    y: &[u8],
-    cert_pks: &[u8],
-) -> Signature {
-    let mut hasher = Sha256::new();
-    hasher.update(nonce_c);
-    hasher.update(x);
-    hasher.update(nonce_s);
-    hasher.update(y);
-    hasher.update(cert_pks);
-    let message_hash = hasher.finalize();
-    
-    signing_key.sign(&message_hash)
+/// State retained after a completed handshake, used to derive
+/// application-specific keying material via the TLS exporter interface
+/// without exposing the master secret or the raw traffic secrets to
+/// whatever higher-level protocol consumes it.
+struct HandshakeState {
+    hash: HashAlgorithm,
+    exporter_master_secret: Vec<u8>,
 }
 
-/// Verify server signature
-fn verify_server_signature(
-    verifying_key: &VerifyingKey,
-    nonce_c: &[u8],
-    x: &[u8],
-    nonce_s: &[u8],
-    y: &[u8],
-    cert_pks: &[u8],
-    signature: &Signature,
-) -> bool {
-    let mut hasher = Sha256::new();
-    hasher.update(nonce_c);
-    hasher.update(x);
-    hasher.update(nonce_s);
-    hasher.update(y);
-    hasher.update(cert_pks);
-    let message_hash = hasher.finalize();
-    
-    verifying_key.verify(&message_hash, signature).is_ok()
-}
-
-/// Compute server MAC: macS = HMAC(K2S, SHA256(nonceC || X || nonceS || Y || σ || cert_pkS || "ServerMAC"))
-fn compute_server_mac(
-    k2s: &[u8; 32],
-    nonce_c: &[u8],
-    x: &[u8],
-    nonce_s: &[u8],
-    y: &[u8],
-    sigma: &[u8],
-    cert_pks: &[u8],
-) -> Vec<u8> {
-    let mut hasher = Sha256::new();
-    hasher.update(nonce_c);
-    hasher.update(x);
-    hasher.update(nonce_s);
-    hasher.update(y);
-    hasher.update(sigma);
-    hasher.update(cert_pks);
-    hasher.update(b"ServerMAC");
-    let message = hasher.finalize();
-    
-    let mut mac = HmacSha256::new_from_slice(k2s).expect("HMAC can take key of any size");
-    mac.update(&message);
-    mac.finalize().into_bytes().to_vec()
-}
-
-/// Compute client MAC: macC = HMAC(K2C, SHA256(nonceC || X || nonceS || Y || σ || cert_pkS || "ClientMAC"))
-fn compute_client_mac(
-    k2c: &[u8; 32],
-    nonce_c: &[u8],
-    x: &[u8],
-    nonce_s: &[u8],
-    y: &[u8],
-    sigma: &[u8],
-    cert_pks: &[u8],
+impl HandshakeState {
+    /// `master_secret` and `transcript_hash` are the same MS and final
+    /// transcript hash that fed KeySchedule3's application traffic secrets.
+    fn new(hash: HashAlgorithm, master_secret: &[u8], transcript_hash: &[u8]) -> Self {
+        let exporter_master_secret =
+            hkdf_expand_label(hash, master_secret, b"exp master", transcript_hash, hash.hash_len());
+        HandshakeState { hash, exporter_master_secret }
+    }
+
+    /// TLS exporter construction: derive `length` bytes of keying material
+    /// bound to this handshake and the caller-supplied `label`/`context`.
+    /// The context is hashed with SHA-256 regardless of the negotiated
+    /// suite, matching the exporter construction as specified.
+    fn exporter(&self, label: &[u8], context: &[u8], length: usize) -> Vec<u8> {
+        let secret = hkdf_expand_label(
+            self.hash,
+            &self.exporter_master_secret,
+            label,
+            &[],
+            self.hash.hash_len(),
+        );
+        let context_hash = Sha256::digest(context).to_vec();
+        hkdf_expand_label(self.hash, &secret, b"exporter", &context_hash, length)
+    }
+}
+
+/// An opaque session ticket binding a PSK identity to the resumption secret
+/// derived from a prior handshake, tagged with the suite it was derived
+/// under so resumption uses a matching hash/AEAD pair.
+struct ResumptionTicket {
+    identity: Vec<u8>,
+    psk: Vec<u8>,
+    suite: CipherSuite,
+}
+
+/// Derive the Resumption Master Secret (label "res master") from the
+/// master secret of a completed handshake, bound to its final transcript
+/// hash, and package it as an opaque ticket the client can present later
+/// to resume with a PSK instead of a fresh signed ServerHello.
+fn issue_resumption_ticket(
+    identity: &[u8],
+    suite: CipherSuite,
+    shared_secret: &[u8],
+    transcript_hash: &[u8],
+) -> ResumptionTicket {
+    let hash = suite.hash();
+    let ms = derive_master_secret_from_hs(hash, &derive_hs(hash, shared_secret));
+    let resumption_master_secret =
+        hkdf_expand_label(hash, &ms, b"res master", transcript_hash, hash.hash_len());
+
+    ResumptionTicket {
+        identity: identity.to_vec(),
+        psk: resumption_master_secret,
+        suite,
+    }
+}
+
+/// Early Secret: ES = HKDF.Extract(0, PSK). Mirrors DeriveHS's own ES step,
+/// but mixes in the PSK instead of an all-zero IKM.
+fn derive_early_secret(hash: HashAlgorithm, psk: &[u8]) -> Vec<u8> {
+    let zeros = vec![0u8; hash.hash_len()];
+    hash.extract(&zeros, psk)
+}
+
+/// DeriveHS variant for PSK-resumption handshakes: continues the existing
+/// "derived es" / Extract(dES, Hash(g^xy)) chain, but starting from an
+/// Early Secret bound to the PSK rather than to an all-zero IKM.
+fn derive_hs_psk(hash: HashAlgorithm, psk: &[u8], shared_secret: &[u8]) -> Vec<u8> {
+    let es = derive_early_secret(hash, psk);
+    let derived_es = hkdf_expand_label(hash, &es, b"derived es", &[], hash.hash_len());
+
+    let gxy_hash = hash.hash(shared_secret);
+    hash.extract(&derived_es, &gxy_hash)
+}
+
+/// Binder key: HKDF-Expand-Label(ES, "res binder", "", hash_len). The
+/// client uses it to prove possession of the PSK before the server commits
+/// to continuing the handshake.
+fn derive_binder_key(hash: HashAlgorithm, early_secret: &[u8]) -> Vec<u8> {
+    hkdf_expand_label(hash, early_secret, b"res binder", &[], hash.hash_len())
+}
+
+/// Client Early Traffic Secret: HKDF-Expand-Label(ES, "c e traffic",
+/// transcript_hash, hash_len). Keys the 0-RTT data the client sends before
+/// ServerHello.
+fn derive_client_early_traffic_secret(
+    hash: HashAlgorithm,
+    early_secret: &[u8],
+    transcript_hash: &[u8],
 ) -> Vec<u8> {
-    let mut hasher = Sha256::new();
-    hasher.update(nonce_c);
-    hasher.update(x);
-    hasher.update(nonce_s);
-    hasher.update(y);
-    hasher.update(sigma);
-    hasher.update(cert_pks);
-    hasher.update(b"ClientMAC");
-    let message = hasher.finalize();
-    
-    let mut mac = HmacSha256::new_from_slice(k2c).expect("HMAC can take key of any size");
-    mac.update(&message);
-    mac.finalize().into_bytes().to_vec()
-}
-
-/// Verify HMAC
-fn verify_mac(key: &[u8; 32], message: &[u8], expected_mac: &[u8]) -> bool {
-    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC can take key of any size");
-    mac.update(message);
-    mac.verify_slice(expected_mac).is_ok()
+    hkdf_expand_label(hash, early_secret, b"c e traffic", transcript_hash, hash.hash_len())
+}
+
+/// PSK binder: HMAC(binder_key, truncated_transcript_hash), where the
+/// truncated transcript covers ClientHello up to (but not including) the
+/// binder itself.
+fn compute_psk_binder(hash: HashAlgorithm, binder_key: &[u8], truncated_transcript_hash: &[u8]) -> Vec<u8> {
+    hash.hmac(binder_key, truncated_transcript_hash)
+}
+
+fn verify_psk_binder(
+    hash: HashAlgorithm,
+    binder_key: &[u8],
+    truncated_transcript_hash: &[u8],
+    binder: &[u8],
+) -> bool {
+    hash.verify_hmac(binder_key, truncated_transcript_hash, binder)
+}
+
+/// Compute server signature: σ = Sign(skS, transcript_hash). The p256
+/// SigningKey always hashes its input with SHA-256 internally regardless
+/// of the negotiated transcript hash, so a Sha384 transcript is simply a
+/// longer message to that inner digest.
+fn compute_server_signature(signing_key: &SigningKey, transcript_hash: &[u8]) -> Signature {
+    signing_key.sign(transcript_hash)
+}
+
+/// Verify server signature
+fn verify_server_signature(verifying_key: &VerifyingKey, transcript_hash: &[u8], signature: &Signature) -> bool {
+    verifying_key.verify(transcript_hash, signature).is_ok()
+}
+
+/// Client-side analog of compute_server_signature/verify_server_signature,
+/// used in client-auth (mTLS) mode where the client also holds a signing
+/// key and proves possession of it with a CertificateVerify-style signature.
+fn compute_client_cert_signature(signing_key: &SigningKey, transcript_hash: &[u8]) -> Signature {
+    signing_key.sign(transcript_hash)
+}
+
+fn verify_client_cert_signature(verifying_key: &VerifyingKey, transcript_hash: &[u8], signature: &Signature) -> bool {
+    verifying_key.verify(transcript_hash, signature).is_ok()
+}
+
+/// Compute server MAC: macS = HMAC(K2S, "server finished" || transcript_hash)
+fn compute_server_mac(hash: HashAlgorithm, k2s: &[u8], transcript_hash: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(b"server finished".len() + transcript_hash.len());
+    data.extend_from_slice(b"server finished");
+    data.extend_from_slice(transcript_hash);
+    hash.hmac(k2s, &data)
+}
+
+/// Compute client MAC: macC = HMAC(K2C, "client finished" || transcript_hash)
+fn compute_client_mac(hash: HashAlgorithm, k2c: &[u8], transcript_hash: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(b"client finished".len() + transcript_hash.len());
+    data.extend_from_slice(b"client finished");
+    data.extend_from_slice(transcript_hash);
+    hash.hmac(k2c, &data)
+}
+
+/// Verify HMAC over `label || transcript_hash`
+fn verify_mac(hash: HashAlgorithm, key: &[u8], label: &[u8], transcript_hash: &[u8], expected_mac: &[u8]) -> bool {
+    let mut data = Vec::with_capacity(label.len() + transcript_hash.len());
+    data.extend_from_slice(label);
+    data.extend_from_slice(transcript_hash);
+    hash.verify_hmac(key, &data, expected_mac)
+}
+
+/// Derive the AEAD key for a direction's traffic secret via
+/// HKDF-Expand(secret, Hash("key")), sized to the negotiated AEAD's key length.
+fn derive_traffic_key(hash: HashAlgorithm, secret: &[u8], aead: AeadAlgorithm) -> Vec<u8> {
+    let info = hash.hash(b"key");
+    hash.expand(secret, &info, aead.key_len())
+}
+
+/// Derive the static per-direction IV via HKDF-Expand(secret, Hash("iv")).
+fn derive_traffic_iv(hash: HashAlgorithm, secret: &[u8]) -> [u8; 12] {
+    let info = hash.hash(b"iv");
+    let iv_bytes = hash.expand(secret, &info, 12);
+    let mut iv = [0u8; 12];
+    iv.copy_from_slice(&iv_bytes);
+    iv
+}
+
+/// A direction is rekeyed once its sequence number gets this close to
+/// wrapping, well within the AEAD usage limits for any of the supported
+/// suites.
+const SEQUENCE_REKEY_THRESHOLD: u64 = u64::MAX - 1024;
+
+/// Signals a peer to rekey the matching receive direction in lockstep with
+/// a sender's rekey of its send direction, mirroring TLS 1.3's KeyUpdate
+/// message.
+struct KeyUpdate;
+
+/// AEAD record layer built on top of the KeySchedule3 application traffic
+/// secrets, using whichever AEAD the cipher suite negotiated. Each
+/// direction gets its own key and static IV, plus an independent record
+/// sequence number; the per-record nonce is the static IV XORed with the
+/// big-endian sequence number in its low 8 bytes, exactly as TLS 1.3
+/// derives per-record nonces. Retains each direction's current traffic
+/// secret (not just the derived key/IV) so it can be rekeyed in place.
+struct SecureChannel {
+    hash: HashAlgorithm,
+    aead: AeadAlgorithm,
+    seal_secret: Vec<u8>,
+    seal_key: Vec<u8>,
+    seal_iv: [u8; 12],
+    seal_seq: u64,
+    open_secret: Vec<u8>,
+    open_key: Vec<u8>,
+    open_iv: [u8; 12],
+    open_seq: u64,
+}
+
+impl SecureChannel {
+    /// `seal_secret` is the traffic secret this side encrypts with,
+    /// `open_secret` is the peer's traffic secret this side decrypts with.
+    fn new(hash: HashAlgorithm, aead: AeadAlgorithm, seal_secret: &[u8], open_secret: &[u8]) -> Self {
+        SecureChannel {
+            hash,
+            aead,
+            seal_secret: seal_secret.to_vec(),
+            seal_key: derive_traffic_key(hash, seal_secret, aead),
+            seal_iv: derive_traffic_iv(hash, seal_secret),
+            seal_seq: 0,
+            open_secret: open_secret.to_vec(),
+            open_key: derive_traffic_key(hash, open_secret, aead),
+            open_iv: derive_traffic_iv(hash, open_secret),
+            open_seq: 0,
+        }
+    }
+
+    fn record_nonce(iv: &[u8; 12], sequence: u64) -> [u8; 12] {
+        let mut nonce = *iv;
+        let seq_bytes = sequence.to_be_bytes();
+        for i in 0..8 {
+            nonce[4 + i] ^= seq_bytes[i];
+        }
+        nonce
+    }
+
+    /// Advance a traffic secret to its next generation via
+    /// HKDF-Expand-Label(secret, "traffic upd", "", hash_len), exactly as
+    /// TLS 1.3's KeyUpdate advances `application_traffic_secret_N`.
+    fn next_generation_secret(&self, secret: &[u8]) -> Vec<u8> {
+        hkdf_expand_label(self.hash, secret, b"traffic upd", &[], self.hash.hash_len())
+    }
+
+    /// Rekey the send direction: derive the next-generation secret,
+    /// recompute the AEAD key and IV, and reset the send sequence number.
+    fn rekey_seal(&mut self) {
+        self.seal_secret = self.next_generation_secret(&self.seal_secret);
+        self.seal_key = derive_traffic_key(self.hash, &self.seal_secret, self.aead);
+        self.seal_iv = derive_traffic_iv(self.hash, &self.seal_secret);
+        self.seal_seq = 0;
+    }
+
+    /// Rekey the receive direction, mirroring a peer's `rekey_seal()` after
+    /// receiving its `KeyUpdate`.
+    fn rekey_open(&mut self) {
+        self.open_secret = self.next_generation_secret(&self.open_secret);
+        self.open_key = derive_traffic_key(self.hash, &self.open_secret, self.aead);
+        self.open_iv = derive_traffic_iv(self.hash, &self.open_secret);
+        self.open_seq = 0;
+    }
+
+    /// Manually request a key update for the send direction, returning the
+    /// `KeyUpdate` control message to send to the peer so it can rekey its
+    /// matching receive direction.
+    fn request_key_update(&mut self) -> KeyUpdate {
+        self.rekey_seal();
+        KeyUpdate
+    }
+
+    /// Seal `plaintext` under the current send sequence number, then
+    /// advance it so the next record gets a fresh nonce. Rekeys
+    /// automatically once the sequence number approaches exhaustion, so a
+    /// long-lived channel never wraps within a single AEAD key.
+    fn seal(&mut self, plaintext: &[u8]) -> Vec<u8> {
+        if self.seal_seq >= SEQUENCE_REKEY_THRESHOLD {
+            self.rekey_seal();
+        }
+        let nonce = Self::record_nonce(&self.seal_iv, self.seal_seq);
+        self.seal_seq += 1;
+        self.aead.encrypt(&self.seal_key, &nonce, plaintext)
+    }
+
+    /// Open `ciphertext` under the current receive sequence number, then
+    /// advance it. Rekeys automatically once the sequence number
+    /// approaches exhaustion, mirroring the sender's automatic rekey.
+    fn open(&mut self, ciphertext: &[u8]) -> Result<Vec<u8>, aead::Error> {
+        if self.open_seq >= SEQUENCE_REKEY_THRESHOLD {
+            self.rekey_open();
+        }
+        let nonce = Self::record_nonce(&self.open_iv, self.open_seq);
+        self.open_seq += 1;
+        self.aead.decrypt(&self.open_key, &nonce, ciphertext)
+    }
 }
 
 // ============================================================================
@@ -276,6 +615,7 @@ fn verify_mac(key: &[u8; 32], message: &[u8], expected_mac: &[u8]) -> bool {
 struct ClientHello {
     nonce: [u8; 32],
     public_key: Vec<u8>,  // DH public key X
+    offered_suites: Vec<CipherSuite>,
 }
 
 struct ServerHello {
@@ -284,58 +624,82 @@ struct ServerHello {
     certificate: Vec<u8>,  // Server's signing public key
     signature: Signature,
     mac: Vec<u8>,
+    selected_suite: CipherSuite,
+}
+
+/// Client CertificateVerify: sent only in client-auth (mTLS) mode, carrying
+/// the client's long-term verifying key and a signature over the transcript
+/// up to (but not including) this message.
+struct ClientCertificate {
+    verifying_key: Vec<u8>,
+    signature: Signature,
 }
 
 struct ClientFinished {
     mac: Vec<u8>,
+    client_certificate: Option<ClientCertificate>,
 }
 
-fn run_handshake() {
-    
+/// Run a full handshake in which the client offers `offered_suites` (in
+/// preference order) and the server selects one. When `client_auth` is
+/// true, the client also authenticates itself with its own signing key
+/// (mTLS): it sends a `ClientCertificate` (verifying key + CertificateVerify
+/// signature) that the server verifies before accepting `ClientFinished`,
+/// and the application keys from KeySchedule3 are bound to both identities.
+/// Returns a resumption ticket for the selected suite.
+fn run_handshake(offered_suites: &[CipherSuite], client_auth: bool) -> ResumptionTicket {
+
     // Server Setup: Generate long-term signing key pair
     let server_signing_key = SigningKey::random(&mut OsRng);
     let server_verifying_key = VerifyingKey::from(&server_signing_key);
     let cert_pks = server_verifying_key.to_encoded_point(false).as_bytes().to_vec();
-    
+
     println!("Server: Generated long-term signing key pair");
     println!("Server Certificate (Public Key): {}\n", hex::encode(&cert_pks));
-    
+
     // Step 1: Client Hello
     println!("--- Step 1: Client Hello ---");
-    
+
     // Client generates ephemeral DH key pair
     let client_dh_secret = EphemeralSecret::random(&mut OsRng);
     let client_dh_public = PublicKey::from(&client_dh_secret);
     let x_bytes = client_dh_public.to_encoded_point(false).as_bytes().to_vec();
-    
+
     // Client generates random nonce
     let mut nonce_c = [0u8; 32];
     rand::Rng::fill(&mut OsRng, &mut nonce_c);
-    
+
     let client_hello = ClientHello {
         nonce: nonce_c,
         public_key: x_bytes.clone(),
+        offered_suites: offered_suites.to_vec(),
     };
-    
+
     println!("Client: Generated nonce_C: {}", hex::encode(&client_hello.nonce));
     println!("Client: Generated DH public key X: {}", hex::encode(&client_hello.public_key));
-    println!("Client -> Server: ClientHello(nonce_C, X)\n");
-    
+    println!("Client: Offered suites: {:?}", client_hello.offered_suites);
+    println!("Client -> Server: ClientHello(nonce_C, X, offered_suites)\n");
+
     // Step 2: Server Hello + Server Finished
     println!("--- Step 2: Server Hello ---");
-    
+
+    // Server selects a cipher suite from the client's offer
+    let suite = CipherSuite::select(&client_hello.offered_suites);
+    let hash = suite.hash();
+    println!("Server: Selected suite {:?}", suite);
+
     // Server generates ephemeral DH key pair
     let server_dh_secret = EphemeralSecret::random(&mut OsRng);
     let server_dh_public = PublicKey::from(&server_dh_secret);
     let y_bytes = server_dh_public.to_encoded_point(false).as_bytes().to_vec();
-    
+
     // Server generates random nonce
     let mut nonce_s = [0u8; 32];
     rand::Rng::fill(&mut OsRng, &mut nonce_s);
-    
+
     println!("Server: Generated nonce_S: {}", hex::encode(&nonce_s));
     println!("Server: Generated DH public key Y: {}", hex::encode(&y_bytes));
-    
+
     // Server computes shared secret
     let client_public_point = EncodedPoint::from_bytes(&client_hello.public_key)
         .expect("Invalid client public key");
@@ -343,58 +707,58 @@ fn run_handshake() {
         .expect("Invalid public key");
     let server_shared_secret = server_dh_secret.diffie_hellman(&client_public_key);
     let shared_secret_bytes = server_shared_secret.raw_secret_bytes();
-    
+
     println!("Server: Computed shared secret g^xy");
-    
-    // Server runs KeySchedule1 and KeySchedule2
-    let (k1c_server, k1s_server) = key_schedule_1(shared_secret_bytes.as_slice());
+
+    // Server runs KeySchedule1 and KeySchedule2, tracking the transcript
+    // as ClientHello and ServerHello's key-exchange fields go by.
+    let (k1c_server, k1s_server) = key_schedule_1(hash, shared_secret_bytes.as_slice());
     println!("Server: Computed K1C, K1S using KeySchedule1");
-    
-    let (k2c_server, k2s_server) = key_schedule_2(
-        &client_hello.nonce,
-        &client_hello.public_key,
-        &nonce_s,
-        &y_bytes,
-        shared_secret_bytes.as_slice(),
-    );
+
+    let mut server_transcript = Transcript::new(hash);
+    server_transcript.update(&client_hello.nonce);
+    server_transcript.update(&client_hello.public_key);
+    for offered in &client_hello.offered_suites {
+        server_transcript.update(&[offered.id()]);
+    }
+    server_transcript.update(&nonce_s);
+    server_transcript.update(&y_bytes);
+    server_transcript.update(&[suite.id()]);
+    let transcript_hash_kx_server = server_transcript.current_hash();
+
+    let (k2c_server, k2s_server) =
+        key_schedule_2(hash, &transcript_hash_kx_server, shared_secret_bytes.as_slice());
     println!("Server: Computed K2C, K2S using KeySchedule2");
-    
-    // Server computes signature
-    let sigma = compute_server_signature(
-        &server_signing_key,
-        &client_hello.nonce,
-        &client_hello.public_key,
-        &nonce_s,
-        &y_bytes,
-        &cert_pks,
-    );
+
+    // Server computes signature over the transcript hash so far
+    let sigma = compute_server_signature(&server_signing_key, &transcript_hash_kx_server);
     println!("Server: Computed signature σ");
-    
-    // Server computes MAC
-    let mac_s = compute_server_mac(
-        &k2s_server,
-        &client_hello.nonce,
-        &client_hello.public_key,
-        &nonce_s,
-        &y_bytes,
-        &sigma.to_bytes(),
-        &cert_pks,
-    );
+
+    // Server computes MAC over the transcript hash including σ and its certificate
+    server_transcript.update(&sigma.to_bytes());
+    server_transcript.update(&cert_pks);
+    let transcript_hash_cert_server = server_transcript.current_hash();
+
+    let mac_s = compute_server_mac(hash, &k2s_server, &transcript_hash_cert_server);
     println!("Server: Computed MAC_S");
-    
+
+    server_transcript.update(&mac_s);
+    let transcript_hash_serverfin_server = server_transcript.current_hash();
+
     let server_hello = ServerHello {
         nonce: nonce_s,
         public_key: y_bytes.clone(),
         certificate: cert_pks.clone(),
         signature: sigma,
         mac: mac_s.clone(),
+        selected_suite: suite,
     };
-    
-    println!("Server -> Client: ServerHello(nonce_S, Y, cert_pkS, σ, MAC_S)\n");
-    
+
+    println!("Server -> Client: ServerHello(nonce_S, Y, cert_pkS, σ, MAC_S, selected_suite)\n");
+
     // Step 3: Client processes Server Hello and sends Finished
     println!("--- Step 3: Client Verification & Finished ---");
-    
+
     // Client computes shared secret
     let server_public_point = EncodedPoint::from_bytes(&server_hello.public_key)
         .expect("Invalid server public key");
@@ -402,138 +766,176 @@ fn run_handshake() {
         .expect("Invalid public key");
     let client_shared_secret = client_dh_secret.diffie_hellman(&server_public_key);
     let client_shared_secret_bytes = client_shared_secret.raw_secret_bytes();
-    
+
     println!("Client: Computed shared secret g^xy");
-    
-    // Client runs KeySchedule1 and KeySchedule2
-    let (k1c_client, k1s_client) = key_schedule_1(client_shared_secret_bytes.as_slice());
+
+    // Client runs KeySchedule1 and KeySchedule2, replaying the same
+    // transcript order the server used.
+    let (k1c_client, k1s_client) = key_schedule_1(hash, client_shared_secret_bytes.as_slice());
     println!("Client: Computed K1C, K1S using KeySchedule1");
-    
-    let (k2c_client, k2s_client) = key_schedule_2(
-        &client_hello.nonce,
-        &client_hello.public_key,
-        &server_hello.nonce,
-        &server_hello.public_key,
-        client_shared_secret_bytes.as_slice(),
-    );
+
+    let mut client_transcript = Transcript::new(hash);
+    client_transcript.update(&client_hello.nonce);
+    client_transcript.update(&client_hello.public_key);
+    for offered in &client_hello.offered_suites {
+        client_transcript.update(&[offered.id()]);
+    }
+    client_transcript.update(&server_hello.nonce);
+    client_transcript.update(&server_hello.public_key);
+    client_transcript.update(&[server_hello.selected_suite.id()]);
+    let transcript_hash_kx_client = client_transcript.current_hash();
+
+    let (k2c_client, k2s_client) =
+        key_schedule_2(hash, &transcript_hash_kx_client, client_shared_secret_bytes.as_slice());
     println!("Client: Computed K2C, K2S using KeySchedule2");
-    
+
     // Client verifies server's signature
     let server_verifying_key_received = VerifyingKey::from_encoded_point(
         &EncodedPoint::from_bytes(&server_hello.certificate).expect("Invalid certificate")
     ).expect("Invalid verifying key");
-    
+
     let signature_valid = verify_server_signature(
         &server_verifying_key_received,
-        &client_hello.nonce,
-        &client_hello.public_key,
-        &server_hello.nonce,
-        &server_hello.public_key,
-        &server_hello.certificate,
+        &transcript_hash_kx_client,
         &server_hello.signature,
     );
-    
+
     if signature_valid {
         println!("Client: Server signature verified");
     } else {
         println!("Client: Server signature verification FAILED");
-        return;
+        std::process::exit(1);
     }
-    
-    // Client verifies server's MAC
-    let mut hasher = Sha256::new();
-    hasher.update(&client_hello.nonce);
-    hasher.update(&client_hello.public_key);
-    hasher.update(&server_hello.nonce);
-    hasher.update(&server_hello.public_key);
-    hasher.update(&server_hello.signature.to_bytes());
-    hasher.update(&server_hello.certificate);
-    hasher.update(b"ServerMAC");
-    let server_mac_message = hasher.finalize();
-    
-    let mac_valid = verify_mac(&k2s_client, &server_mac_message, &server_hello.mac);
-    
+
+    // Client verifies server's MAC over the transcript hash including σ and the certificate
+    client_transcript.update(&server_hello.signature.to_bytes());
+    client_transcript.update(&server_hello.certificate);
+    let transcript_hash_cert_client = client_transcript.current_hash();
+
+    let mac_valid = verify_mac(
+        hash,
+        &k2s_client,
+        b"server finished",
+        &transcript_hash_cert_client,
+        &server_hello.mac,
+    );
+
     if mac_valid {
         println!("Client: Server MAC verified");
     } else {
         println!("Client: Server MAC verification FAILED");
-        return;
+        std::process::exit(1);
     }
-    
+
+    client_transcript.update(&server_hello.mac);
+    let transcript_hash_serverfin_client = client_transcript.current_hash();
+
+    // In client-auth mode, the client proves possession of its own signing
+    // key with a CertificateVerify-style signature over the transcript so
+    // far, before Finished is computed.
+    let client_signing_key = if client_auth {
+        Some(SigningKey::random(&mut OsRng))
+    } else {
+        None
+    };
+
+    let client_certificate = client_signing_key.as_ref().map(|signing_key| {
+        let verifying_key = VerifyingKey::from(signing_key);
+        let cert_pkc = verifying_key.to_encoded_point(false).as_bytes().to_vec();
+        let sigma_c = compute_client_cert_signature(signing_key, &transcript_hash_serverfin_client);
+        println!("Client: Computed client certificate signature σ_C");
+
+        client_transcript.update(&cert_pkc);
+        client_transcript.update(&sigma_c.to_bytes());
+
+        ClientCertificate {
+            verifying_key: cert_pkc,
+            signature: sigma_c,
+        }
+    });
+
+    // KeySchedule3's input hash is taken after the optional client
+    // certificate, so application keys are bound to both identities when
+    // client-auth is in use.
+    let transcript_hash_clientfin_client = client_transcript.current_hash();
+
     // Client computes MAC
-    let mac_c = compute_client_mac(
-        &k2c_client,
-        &client_hello.nonce,
-        &client_hello.public_key,
-        &server_hello.nonce,
-        &server_hello.public_key,
-        &server_hello.signature.to_bytes(),
-        &server_hello.certificate,
-    );
+    let mac_c = compute_client_mac(hash, &k2c_client, &transcript_hash_clientfin_client);
     println!("Client: Computed MAC_C");
-    
+
     let client_finished = ClientFinished {
         mac: mac_c.clone(),
+        client_certificate,
     };
-    
-    println!("Client -> Server: ClientFinished(MAC_C)\n");
-    
+
+    if client_finished.client_certificate.is_some() {
+        println!("Client -> Server: ClientCertificate(cert_pkC, σ_C), ClientFinished(MAC_C)\n");
+    } else {
+        println!("Client -> Server: ClientFinished(MAC_C)\n");
+    }
+
     // ========================================================================
     // Step 4: Server verifies Client Finished
     // ========================================================================
     println!("--- Step 4: Server Final Verification ---");
-    
-    // Server verifies client's MAC
-    let mut hasher = Sha256::new();
-    hasher.update(&client_hello.nonce);
-    hasher.update(&client_hello.public_key);
-    hasher.update(&server_hello.nonce);
-    hasher.update(&server_hello.public_key);
-    hasher.update(&server_hello.signature.to_bytes());
-    hasher.update(&server_hello.certificate);
-    hasher.update(b"ClientMAC");
-    let client_mac_message = hasher.finalize();
-    
-    let client_mac_valid = verify_mac(&k2c_server, &client_mac_message, &client_finished.mac);
-    
+
+    // Server verifies the client's certificate signature, if present, before
+    // extending the transcript the same way the client did.
+    if let Some(client_certificate) = &client_finished.client_certificate {
+        let client_verifying_key = VerifyingKey::from_encoded_point(
+            &EncodedPoint::from_bytes(&client_certificate.verifying_key).expect("Invalid client certificate")
+        ).expect("Invalid client verifying key");
+
+        let client_signature_valid = verify_client_cert_signature(
+            &client_verifying_key,
+            &transcript_hash_serverfin_server,
+            &client_certificate.signature,
+        );
+
+        if client_signature_valid {
+            println!("Server: ✓ Client certificate signature verified");
+        } else {
+            println!("Server: ✗ Client certificate signature verification FAILED");
+            std::process::exit(1);
+        }
+
+        server_transcript.update(&client_certificate.verifying_key);
+        server_transcript.update(&client_certificate.signature.to_bytes());
+    }
+
+    let transcript_hash_clientfin_server = server_transcript.current_hash();
+
+    // Server verifies client's MAC over the transcript hash extended with the optional client certificate
+    let client_mac_valid = verify_mac(
+        hash,
+        &k2c_server,
+        b"client finished",
+        &transcript_hash_clientfin_server,
+        &client_finished.mac,
+    );
+
     if client_mac_valid {
         println!("Server: ✓ Client MAC verified");
     } else {
         println!("Server: ✗ Client MAC verification FAILED");
-        return;
+        std::process::exit(1);
     }
-    
+
     // ========================================================================
     // Step 5: Both parties derive application keys using KeySchedule3
     // ========================================================================
     println!("\n--- Step 5: Application Key Derivation ---");
-    
-    // Client derives application keys
-    let (k3c_client, k3s_client) = key_schedule_3(
-        &client_hello.nonce,
-        &client_hello.public_key,
-        &server_hello.nonce,
-        &server_hello.public_key,
-        client_shared_secret_bytes.as_slice(),
-        &server_hello.signature.to_bytes(),
-        &server_hello.certificate,
-        &server_hello.mac,
-    );
+
+    // Client derives application keys, bound to the transcript hash up to and including the optional client certificate
+    let (k3c_client, k3s_client) =
+        key_schedule_3(hash, &transcript_hash_clientfin_client, client_shared_secret_bytes.as_slice());
     println!("Client: Computed K3C, K3S using KeySchedule3");
-    
+
     // Server derives application keys
-    let (k3c_server, k3s_server) = key_schedule_3(
-        &client_hello.nonce,
-        &client_hello.public_key,
-        &server_hello.nonce,
-        &server_hello.public_key,
-        shared_secret_bytes.as_slice(),
-        &server_hello.signature.to_bytes(),
-        &server_hello.certificate,
-        &server_hello.mac,
-    );
+    let (k3c_server, k3s_server) =
+        key_schedule_3(hash, &transcript_hash_clientfin_server, shared_secret_bytes.as_slice());
     println!("Server: Computed K3C, K3S using KeySchedule3");
-    
+
     // Verification: Check that both parties have the same keys
     println!("\nHandshake Complete");
     println!("\nKey Agreement Verification:");
@@ -543,14 +945,256 @@ fn run_handshake() {
     println!("K2S match: {}", k2s_client == k2s_server);
     println!("K3C match: {}", k3c_client == k3c_server);
     println!("K3S match: {}", k3s_client == k3s_server);
-    
+
     println!("\nFinal Application Keys:");
-    println!("K3C (Client Encryption Key): {}", hex::encode(k3c_client));
-    println!("K3S (Server Encryption Key): {}", hex::encode(k3s_client));
-    
+    println!("K3C (Client Encryption Key): {}", hex::encode(&k3c_client));
+    println!("K3S (Server Encryption Key): {}", hex::encode(&k3s_client));
+
     println!("\n Handshake successful! Secure channel established.");
+
+    // ========================================================================
+    // Step 6: Exercise the application data channel
+    // ========================================================================
+    println!("\n--- Step 6: Secure Channel Data Exchange ---");
+
+    let aead = suite.aead();
+
+    // Client seals under K3C, opens under K3S; server is the mirror image.
+    let mut client_channel = SecureChannel::new(hash, aead, &k3c_client, &k3s_client);
+    let mut server_channel = SecureChannel::new(hash, aead, &k3s_server, &k3c_server);
+
+    let client_request = b"GET /secure-resource HTTP/1.1";
+    let sealed_request = client_channel.seal(client_request);
+    println!("Client -> Server: {} bytes sealed", sealed_request.len());
+
+    let opened_request = server_channel
+        .open(&sealed_request)
+        .expect("server failed to open client record");
+    println!(
+        "Server: opened client record matches: {}",
+        opened_request == client_request
+    );
+
+    let server_response = b"HTTP/1.1 200 OK";
+    let sealed_response = server_channel.seal(server_response);
+    println!("Server -> Client: {} bytes sealed", sealed_response.len());
+
+    let opened_response = client_channel
+        .open(&sealed_response)
+        .expect("client failed to open server record");
+    println!(
+        "Client: opened server record matches: {}",
+        opened_response == server_response
+    );
+
+    // ========================================================================
+    // Step 6b: Key update mid-channel
+    // ========================================================================
+    println!("\n--- Step 6b: Key Update ---");
+
+    // Client proactively rekeys its send direction and signals the server
+    // with a KeyUpdate control message so the server rekeys its matching
+    // receive direction in lockstep.
+    let _key_update = client_channel.request_key_update();
+    server_channel.rekey_open();
+    println!("Client -> Server: KeyUpdate");
+    println!(
+        "Post-update secrets match: {}",
+        client_channel.seal_secret == server_channel.open_secret
+    );
+
+    let client_request_2 = b"GET /secure-resource HTTP/1.1 (post-update)";
+    let sealed_request_2 = client_channel.seal(client_request_2);
+    let opened_request_2 = server_channel
+        .open(&sealed_request_2)
+        .expect("server failed to open post-update client record");
+    println!(
+        "Server: opened post-update client record matches: {}",
+        opened_request_2 == client_request_2
+    );
+
+    // ========================================================================
+    // Step 7: Server issues a resumption ticket for a future PSK handshake
+    // ========================================================================
+    println!("\n--- Step 7: Session Ticket Issuance ---");
+
+    let ticket = issue_resumption_ticket(
+        b"ticket-1",
+        suite,
+        shared_secret_bytes.as_slice(),
+        &transcript_hash_clientfin_server,
+    );
+    println!(
+        "Server -> Client: NewSessionTicket(identity = {})",
+        String::from_utf8_lossy(&ticket.identity)
+    );
+
+    // ========================================================================
+    // Step 8: Exporter interface for application-specific keying material
+    // ========================================================================
+    println!("\n--- Step 8: Exporter Interface ---");
+
+    let ms_client = derive_master_secret_from_hs(hash, &derive_hs(hash, client_shared_secret_bytes.as_slice()));
+    let ms_server = derive_master_secret_from_hs(hash, &derive_hs(hash, shared_secret_bytes.as_slice()));
+
+    let client_handshake_state = HandshakeState::new(hash, &ms_client, &transcript_hash_clientfin_client);
+    let server_handshake_state = HandshakeState::new(hash, &ms_server, &transcript_hash_clientfin_server);
+
+    let client_exported = client_handshake_state.exporter(b"EXPERIMENTAL channel binding", b"demo context", 32);
+    let server_exported = server_handshake_state.exporter(b"EXPERIMENTAL channel binding", b"demo context", 32);
+
+    println!(
+        "Exported keying material matches: {}",
+        client_exported == server_exported
+    );
+
+    ticket
+}
+
+/// Resume a session with a PSK derived from a prior handshake's ticket,
+/// mixed into a fresh ECDHE exchange (PSK-DHE). The client also sends
+/// 0-RTT early application data, encrypted under the Client Early Traffic
+/// Secret, which the server can only decrypt after verifying the PSK
+/// binder that accompanies it.
+fn run_psk_resumption_handshake(ticket: &ResumptionTicket) {
+    println!("\n=== PSK Resumption Handshake ===\n");
+
+    let suite = ticket.suite;
+    let hash = suite.hash();
+
+    // Step 1: Client Hello carrying the PSK identity, binder, and 0-RTT data
+    println!("--- Step 1: Client Hello (PSK) ---");
+
+    let client_dh_secret = EphemeralSecret::random(&mut OsRng);
+    let client_dh_public = PublicKey::from(&client_dh_secret);
+    let x_bytes = client_dh_public.to_encoded_point(false).as_bytes().to_vec();
+
+    let mut nonce_c = [0u8; 32];
+    rand::Rng::fill(&mut OsRng, &mut nonce_c);
+
+    // The truncated transcript covers ClientHello's fields and the PSK
+    // identity, but not the binder itself.
+    let mut client_transcript = Transcript::new(hash);
+    client_transcript.update(&nonce_c);
+    client_transcript.update(&x_bytes);
+    client_transcript.update(&ticket.identity);
+    let truncated_transcript_hash_client = client_transcript.current_hash();
+
+    let early_secret_client = derive_early_secret(hash, &ticket.psk);
+    let binder_key_client = derive_binder_key(hash, &early_secret_client);
+    let binder = compute_psk_binder(hash, &binder_key_client, &truncated_transcript_hash_client);
+    println!("Client: Computed PSK binder over truncated transcript");
+
+    let client_early_traffic_secret = derive_client_early_traffic_secret(
+        hash,
+        &early_secret_client,
+        &truncated_transcript_hash_client,
+    );
+    let aead = suite.aead();
+    let early_key = derive_traffic_key(hash, &client_early_traffic_secret, aead);
+    let early_iv = derive_traffic_iv(hash, &client_early_traffic_secret);
+
+    let early_data = b"GET /warm-cache-hit HTTP/1.1";
+    let sealed_early_data = aead.encrypt(&early_key, &early_iv, early_data.as_slice());
+
+    println!(
+        "Client -> Server: ClientHello(nonce_C, X, psk_identity, binder) + {} bytes of 0-RTT data\n",
+        sealed_early_data.len()
+    );
+
+    // Step 2: Server verifies the binder before accepting the 0-RTT data
+    println!("--- Step 2: Server PSK + Early Data Verification ---");
+
+    let mut server_transcript = Transcript::new(hash);
+    server_transcript.update(&nonce_c);
+    server_transcript.update(&x_bytes);
+    server_transcript.update(&ticket.identity);
+    let truncated_transcript_hash_server = server_transcript.current_hash();
+
+    let early_secret_server = derive_early_secret(hash, &ticket.psk);
+    let binder_key_server = derive_binder_key(hash, &early_secret_server);
+    let binder_valid =
+        verify_psk_binder(hash, &binder_key_server, &truncated_transcript_hash_server, &binder);
+
+    if !binder_valid {
+        println!("Server: ✗ PSK binder verification FAILED, rejecting resumption");
+        return;
+    }
+    println!("Server: ✓ PSK binder verified");
+
+    let server_early_traffic_secret = derive_client_early_traffic_secret(
+        hash,
+        &early_secret_server,
+        &truncated_transcript_hash_server,
+    );
+    let server_early_key = derive_traffic_key(hash, &server_early_traffic_secret, aead);
+    let server_early_iv = derive_traffic_iv(hash, &server_early_traffic_secret);
+
+    match aead.decrypt(&server_early_key, &server_early_iv, sealed_early_data.as_slice()) {
+        Ok(opened_early_data) => println!(
+            "Server: ✓ accepted 0-RTT data: {}",
+            String::from_utf8_lossy(&opened_early_data)
+        ),
+        Err(_) => println!("Server: ✗ rejected 0-RTT data, falling back to 1-RTT only"),
+    }
+
+    // The rest of the handshake proceeds like a fresh ECDHE exchange, but
+    // DeriveHS now starts from the PSK-bound Early Secret instead of an
+    // all-zero IKM, so every later secret is tied to both the PSK and the
+    // fresh (ephemeral, ephemeral) DH shared secret.
+    println!("--- Step 3: PSK-DHE Key Schedule ---");
+
+    let server_dh_secret = EphemeralSecret::random(&mut OsRng);
+    let server_dh_public = PublicKey::from(&server_dh_secret);
+    let y_bytes = server_dh_public.to_encoded_point(false).as_bytes().to_vec();
+
+    let client_public_point =
+        EncodedPoint::from_bytes(&x_bytes).expect("Invalid client public key");
+    let client_public_key =
+        PublicKey::from_encoded_point(&client_public_point).expect("Invalid public key");
+    let server_shared_secret = server_dh_secret.diffie_hellman(&client_public_key);
+    let shared_secret_bytes = server_shared_secret.raw_secret_bytes();
+
+    let y_point = EncodedPoint::from_bytes(&y_bytes).expect("Invalid server public key");
+    let y_key = PublicKey::from_encoded_point(&y_point).expect("Invalid public key");
+    let client_shared_secret = client_dh_secret.diffie_hellman(&y_key);
+    let client_shared_secret_bytes = client_shared_secret.raw_secret_bytes();
+
+    server_transcript.update(&y_bytes);
+    client_transcript.update(&y_bytes);
+    let transcript_hash_kx_server = server_transcript.current_hash();
+    let transcript_hash_kx_client = client_transcript.current_hash();
+
+    let hs_server = derive_hs_psk(hash, &ticket.psk, shared_secret_bytes.as_slice());
+    let hs_client = derive_hs_psk(hash, &ticket.psk, client_shared_secret_bytes.as_slice());
+
+    let (k2c_server, k2s_server) = key_schedule_2_from_hs(hash, &hs_server, &transcript_hash_kx_server);
+    let (k2c_client, k2s_client) = key_schedule_2_from_hs(hash, &hs_client, &transcript_hash_kx_client);
+    println!("Client/Server: Computed K2C, K2S using the PSK-bound KeySchedule2");
+
+    let (k3c_server, k3s_server) = key_schedule_3_from_hs(hash, &hs_server, &transcript_hash_kx_server);
+    let (k3c_client, k3s_client) = key_schedule_3_from_hs(hash, &hs_client, &transcript_hash_kx_client);
+    println!("Client/Server: Computed K3C, K3S using the PSK-bound KeySchedule3");
+
+    println!("\nKey Agreement Verification:");
+    println!("K2C match: {}", k2c_client == k2c_server);
+    println!("K2S match: {}", k2s_client == k2s_server);
+    println!("K3C match: {}", k3c_client == k3c_server);
+    println!("K3S match: {}", k3s_client == k3s_server);
+
+    println!("\nPSK resumption handshake successful! 1-RTT keys confirmed, 0-RTT data delivered ahead of ServerHello.");
 }
 
 fn main() {
-    run_handshake();
-}
\ No newline at end of file
+    let ticket = run_handshake(&CipherSuite::ALL, false);
+    run_psk_resumption_handshake(&ticket);
+
+    println!("\n=== Verifying every supported cipher suite completes a handshake ===");
+    for suite in CipherSuite::ALL {
+        println!("\n--- Suite under test: {:?} ---", suite);
+        run_handshake(&[suite], false);
+    }
+
+    println!("\n=== Mutual Authentication (mTLS) Handshake ===");
+    run_handshake(&CipherSuite::ALL, true);
+}
@@ -1,7 +1,10 @@
+use hmac::{Hmac, Mac};
 use num_bigint::{BigInt, BigUint, ToBigInt};
 use num_traits::{One, Zero};
 use sha2::{Sha256, Digest};
 
+type HmacSha256 = Hmac<Sha256>;
+
 /// Simple elliptic curve point structure
 #[derive(Debug, Clone, PartialEq)]
 struct Point {
@@ -165,6 +168,116 @@ fn sign_with_nonce(curve: &EllipticCurve, private_key: &BigInt, message: &[u8],
     Signature { r, s }
 }
 
+/// Left-pads (or truncates, from the left) `x` to exactly `len` bytes -
+/// the `int2octets`/`bits2octets` encoding from RFC 6979 section 2.3.3.
+fn int2octets(x: &BigInt, len: usize) -> Vec<u8> {
+    let (_, bytes) = x.to_bytes_be();
+    if bytes.len() >= len {
+        bytes[bytes.len() - len..].to_vec()
+    } else {
+        let mut padded = vec![0u8; len - bytes.len()];
+        padded.extend_from_slice(&bytes);
+        padded
+    }
+}
+
+/// Interprets a hash as a big-endian integer, per RFC 6979's `bits2int`.
+/// `secp256k1`'s order is exactly 256 bits, the same width as a SHA-256
+/// digest, so no bit-shifting is needed here.
+fn bits2int(hash: &[u8; 32]) -> BigInt {
+    BigInt::from_bytes_be(num_bigint::Sign::Plus, hash)
+}
+
+/// `bits2octets`: reduce the hash (as an integer) mod `n`, then encode it
+/// at the same byte length as `n` - the message-dependent input folded
+/// into the HMAC-DRBG alongside the private key.
+fn bits2octets(hash: &[u8; 32], n: &BigInt, rlen: usize) -> Vec<u8> {
+    let z = bits2int(hash);
+    let z = if z >= *n { z - n } else { z };
+    int2octets(&z, rlen)
+}
+
+/// Deterministic ECDSA signing per RFC 6979: the nonce `k` is derived from
+/// the private key and message hash via an HMAC-DRBG instead of coming
+/// from the caller, so the same (key, message) pair always produces the
+/// same signature and a nonce can never be reused across distinct
+/// messages. This gives a safe default signer while keeping
+/// `sign_with_nonce` around for the nonce-reuse teaching demo above.
+fn sign(curve: &EllipticCurve, private_key: &BigInt, message: &[u8]) -> Signature {
+    let mut hasher = Sha256::new();
+    hasher.update(message);
+    let message_hash: [u8; 32] = hasher.finalize().into();
+    let z = bits2int(&message_hash);
+
+    let rlen = ((curve.n.bits() as usize) + 7) / 8;
+    let priv_octets = int2octets(private_key, rlen);
+    let h1_octets = bits2octets(&message_hash, &curve.n, rlen);
+
+    let hlen = 32;
+    let mut v = vec![0x01u8; hlen];
+    let mut k = vec![0x00u8; hlen];
+
+    let mut mac = HmacSha256::new_from_slice(&k).unwrap();
+    mac.update(&v);
+    mac.update(&[0x00]);
+    mac.update(&priv_octets);
+    mac.update(&h1_octets);
+    k = mac.finalize().into_bytes().to_vec();
+
+    let mut mac = HmacSha256::new_from_slice(&k).unwrap();
+    mac.update(&v);
+    v = mac.finalize().into_bytes().to_vec();
+
+    let mut mac = HmacSha256::new_from_slice(&k).unwrap();
+    mac.update(&v);
+    mac.update(&[0x01]);
+    mac.update(&priv_octets);
+    mac.update(&h1_octets);
+    k = mac.finalize().into_bytes().to_vec();
+
+    let mut mac = HmacSha256::new_from_slice(&k).unwrap();
+    mac.update(&v);
+    v = mac.finalize().into_bytes().to_vec();
+
+    loop {
+        let mut t = Vec::new();
+        while t.len() < rlen {
+            let mut mac = HmacSha256::new_from_slice(&k).unwrap();
+            mac.update(&v);
+            v = mac.finalize().into_bytes().to_vec();
+            t.extend_from_slice(&v);
+        }
+
+        let candidate_k = BigInt::from_bytes_be(num_bigint::Sign::Plus, &t[..rlen]);
+        if candidate_k >= BigInt::one() && candidate_k < curve.n {
+            let r_point = curve.scalar_mult(&candidate_k, &curve.g);
+            let r = &r_point.x % &curve.n;
+            let r = if r < BigInt::zero() { r + &curve.n } else { r };
+
+            if !r.is_zero() {
+                let k_inv = curve.mod_inverse(&candidate_k, &curve.n).unwrap();
+                let s = (&k_inv * (&z + &r * private_key)) % &curve.n;
+                let s = if s < BigInt::zero() { s + &curve.n } else { s };
+
+                if !s.is_zero() {
+                    return Signature { r, s };
+                }
+            }
+        }
+
+        // Candidate rejected (out of range, or r/s came out to zero): fold
+        // in another round per RFC 6979 section 3.2 step (h.3) and retry.
+        let mut mac = HmacSha256::new_from_slice(&k).unwrap();
+        mac.update(&v);
+        mac.update(&[0x00]);
+        k = mac.finalize().into_bytes().to_vec();
+
+        let mut mac = HmacSha256::new_from_slice(&k).unwrap();
+        mac.update(&v);
+        v = mac.finalize().into_bytes().to_vec();
+    }
+}
+
 /// Recover private key from two signatures with the same nonce
 fn recover_private_key(
     curve: &EllipticCurve,
@@ -265,5 +378,21 @@ fn main() {
             println!("Failed to recover private key");
         }
     }
-    
+
+    // SAFE: RFC 6979 deterministic nonce derivation never reuses k across
+    // distinct messages, so the attack above has no foothold here.
+    println!("\nDeterministic Signing (RFC 6979)");
+
+    let safe_sig1 = sign(&curve, &private_key, message1);
+    let safe_sig2 = sign(&curve, &private_key, message2);
+
+    println!("Signature 1: r={}, s={}", safe_sig1.r, safe_sig1.s);
+    println!("Signature 2: r={}, s={}", safe_sig2.r, safe_sig2.s);
+    println!("Nonces differ (r1 != r2): {}", safe_sig1.r != safe_sig2.r);
+
+    let safe_sig1_again = sign(&curve, &private_key, message1);
+    println!(
+        "Re-signing message 1 is deterministic: {}",
+        safe_sig1.r == safe_sig1_again.r && safe_sig1.s == safe_sig1_again.s
+    );
 }
\ No newline at end of file
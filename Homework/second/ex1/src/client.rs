@@ -1,11 +1,12 @@
-use crate::crypto::{compute_verify_data, random_bytes, KeySchedule, TrafficCipher};
+use crate::crypto::{compute_verify_data, random_bytes, AeadAlgorithm, KeySchedule, TrafficCipher};
 use crate::protocol::*;
 use ml_dsa::MlDsa65;
 use ml_kem::{kem::{Decapsulate, DecapsulationKey}, EncodedSizeUser, KemCore, MlKem768};
 use rand::rngs::OsRng;
 use sha3::Digest;
-use signature::Verifier;
+use signature::{Signer, SignatureEncoding, Verifier};
 use thiserror::Error;
+use x25519_dalek::{EphemeralSecret, PublicKey as X25519PublicKey};
 
 #[derive(Debug, Error)]
 pub enum ClientError {
@@ -21,16 +22,33 @@ pub enum ClientError {
     SignatureVerificationFailed,
     #[error("Finished verification failed")]
     FinishedVerificationFailed,
+    #[error("No mutually supported ALPN protocol")]
+    NoAlpnOverlap,
+    #[error("Server requested a client certificate, but this client has no identity configured (see Client::with_identity)")]
+    ClientAuthRequiredButNotConfigured,
 }
 
 /// PQ-TLS Client
 pub struct Client {
     config: ClientConfig,
+    identity: Option<ClientIdentity>,
+}
+
+/// A client's ML-DSA signing identity, presented via `Certificate` +
+/// `CertificateVerify` when the server asks for mutual authentication. A
+/// `Client` with no identity simply fails the handshake if the server
+/// requests one.
+pub struct ClientIdentity {
+    pub signing_key: ml_dsa::SigningKey<MlDsa65>,
+    pub verifying_key: ml_dsa::VerifyingKey<MlDsa65>,
 }
 
 pub struct ClientConfig {
     pub cipher_suites: Vec<CipherSuite>,
     pub server_name: Option<String>,
+    /// Application protocols to offer via ALPN, in preference order. An
+    /// empty list (the default) omits the `Extension::Alpn` entirely.
+    pub alpn_protocols: Vec<Vec<u8>>,
 }
 
 impl Default for ClientConfig {
@@ -38,27 +56,76 @@ impl Default for ClientConfig {
         Self {
             cipher_suites: vec![CipherSuite::MlKem768MlDsa65Aes256Gcm],
             server_name: None,
+            alpn_protocols: Vec::new(),
         }
     }
 }
 
 impl Client {
     pub fn new(config: ClientConfig) -> Self {
-        Self { config }
+        Self { config, identity: None }
+    }
+
+    /// Create a client that will present `identity` as its certificate
+    /// whenever the server requests mutual authentication.
+    pub fn with_identity(config: ClientConfig, identity: ClientIdentity) -> Self {
+        Self { config, identity: Some(identity) }
     }
 
     /// Initiate the handshake by creating ClientHello
     pub fn start_handshake(&self) -> Result<(ClientHandshakeState, Vec<u8>), ClientError> {
+        self.start_handshake_inner(None, None)
+    }
+
+    /// Like `start_handshake`, but offers a previously issued resumption
+    /// ticket via `Extension::PreSharedKey`. If the server accepts it
+    /// (echoed back in its `ServerHello`), `complete_handshake` mixes
+    /// `resumption_secret` into the key schedule instead of relying solely
+    /// on a cold ML-KEM handshake; otherwise it falls back transparently.
+    /// `resumption_secret` must be the value `key_schedule.derive_resumption_master_secret()`
+    /// returned for the session that issued `ticket`.
+    pub fn start_resumption_handshake(
+        &self,
+        ticket: Vec<u8>,
+        resumption_secret: [u8; 32],
+    ) -> Result<(ClientHandshakeState, Vec<u8>), ClientError> {
+        let psk_extension = Extension::PreSharedKey {
+            identity: ticket,
+            obfuscated_age: 0,
+        };
+        self.start_handshake_inner(Some(psk_extension), Some(resumption_secret))
+    }
+
+    fn start_handshake_inner(
+        &self,
+        psk_extension: Option<Extension>,
+        resumption_secret: Option<[u8; 32]>,
+    ) -> Result<(ClientHandshakeState, Vec<u8>), ClientError> {
         let mut rng = OsRng;
 
         // 1. Generate ML-KEM key pair
         let (decapsulation_key, encapsulation_key) = MlKem768::generate(&mut rng);
 
+        // 1b. Optimistically generate an X25519 keypair too, in case the
+        // server negotiates the hybrid suite. Cheap enough to always offer.
+        let x25519_secret = EphemeralSecret::random_from_rng(&mut rng);
+        let x25519_public = X25519PublicKey::from(&x25519_secret);
+
         // 2. Create ClientHello
         let client_random = random_bytes::<32>();
-        let mut extensions = vec![Extension::KeyShare {
-            encapsulation_key: encapsulation_key.as_bytes().to_vec(),
-        }];
+        let mut extensions = vec![
+            Extension::KeyShare {
+                encapsulation_key: encapsulation_key.as_bytes().to_vec(),
+            },
+            Extension::HybridKeyShare {
+                x25519_public: x25519_public.to_bytes(),
+                encapsulation_key: encapsulation_key.as_bytes().to_vec(),
+            },
+        ];
+
+        if let Some(psk_extension) = psk_extension {
+            extensions.push(psk_extension);
+        }
 
         if let Some(server_name) = &self.config.server_name {
             extensions.push(Extension::ServerName {
@@ -66,6 +133,12 @@ impl Client {
             });
         }
 
+        if !self.config.alpn_protocols.is_empty() {
+            extensions.push(Extension::Alpn {
+                protocols: self.config.alpn_protocols.clone(),
+            });
+        }
+
         extensions.push(Extension::SupportedVersions {
             versions: vec![PQ_TLS_VERSION],
         });
@@ -83,6 +156,8 @@ impl Client {
 
         let state = ClientHandshakeState {
             decapsulation_key,
+            x25519_secret,
+            resumption_secret,
             client_random,
             handshake_messages: vec![client_hello_data.clone()],
         };
@@ -113,25 +188,96 @@ impl Client {
 
         let server_hello: ServerHello = bincode::deserialize(&server_hello_msg.payload)?;
         let server_random = server_hello.random;
+        let aead_algorithm = server_hello.cipher_suite.aead_algorithm();
 
-        // 2. Extract ML-KEM ciphertext
-        let ciphertext_bytes = server_hello
+        // 1b. Resolve ALPN: if we offered a non-empty protocol list, the
+        // server must have echoed back a selection, mirroring TLS's
+        // `no_application_protocol` alert when it doesn't.
+        let alpn_protocol = server_hello
             .extensions
             .iter()
             .find_map(|ext| match ext {
-                Extension::KeyShareCiphertext { ciphertext } => Some(ciphertext.clone()),
+                Extension::AlpnSelected { protocol } => Some(protocol.clone()),
                 _ => None,
-            })
-            .ok_or_else(|| ClientError::HandshakeFailed("No key share ciphertext".to_string()))?;
+            });
 
-        // 3. Decapsulate to get shared secret
-        let ciphertext: ml_kem::Ciphertext<MlKem768> = ciphertext_bytes.as_slice().try_into()
-            .map_err(|_| ClientError::HandshakeFailed("Invalid ciphertext size".to_string()))?;
-        
-        let shared_secret = state
-            .decapsulation_key
-            .decapsulate(&ciphertext)
-            .map_err(|_| ClientError::HandshakeFailed("Decapsulation failed".to_string()))?;
+        if !self.config.alpn_protocols.is_empty() && alpn_protocol.is_none() {
+            return Err(ClientError::NoAlpnOverlap);
+        }
+
+        // 2-3. Recover the shared secret: plain ML-KEM decapsulation, or -
+        // if the hybrid suite was negotiated - ML-KEM decapsulation combined
+        // with an X25519 ECDH against the server's ephemeral public.
+        let shared_secret = if server_hello.cipher_suite.is_hybrid_key_exchange() {
+            let (server_x25519_public, ciphertext_bytes) = server_hello
+                .extensions
+                .iter()
+                .find_map(|ext| match ext {
+                    Extension::HybridKeyShareCiphertext { x25519_public, ciphertext } => {
+                        Some((*x25519_public, ciphertext.clone()))
+                    }
+                    _ => None,
+                })
+                .ok_or_else(|| {
+                    ClientError::HandshakeFailed("No hybrid key share ciphertext".to_string())
+                })?;
+
+            let ciphertext: ml_kem::Ciphertext<MlKem768> = ciphertext_bytes.as_slice().try_into()
+                .map_err(|_| ClientError::HandshakeFailed("Invalid ciphertext size".to_string()))?;
+            let mlkem_ss = state
+                .decapsulation_key
+                .decapsulate(&ciphertext)
+                .map_err(|_| ClientError::HandshakeFailed("Decapsulation failed".to_string()))?;
+
+            let x25519_ss = state
+                .x25519_secret
+                .diffie_hellman(&X25519PublicKey::from(server_x25519_public));
+
+            // Fixed concatenation order: classical first, then PQ.
+            let mut combined = Vec::with_capacity(32 + mlkem_ss.as_ref().len());
+            combined.extend_from_slice(x25519_ss.as_bytes());
+            combined.extend_from_slice(mlkem_ss.as_ref());
+            combined
+        } else {
+            let ciphertext_bytes = server_hello
+                .extensions
+                .iter()
+                .find_map(|ext| match ext {
+                    Extension::KeyShareCiphertext { ciphertext } => Some(ciphertext.clone()),
+                    _ => None,
+                })
+                .ok_or_else(|| ClientError::HandshakeFailed("No key share ciphertext".to_string()))?;
+
+            let ciphertext: ml_kem::Ciphertext<MlKem768> = ciphertext_bytes.as_slice().try_into()
+                .map_err(|_| ClientError::HandshakeFailed("Invalid ciphertext size".to_string()))?;
+
+            state
+                .decapsulation_key
+                .decapsulate(&ciphertext)
+                .map_err(|_| ClientError::HandshakeFailed("Decapsulation failed".to_string()))?
+                .as_ref()
+                .to_vec()
+        };
+
+        // 3b. If we offered a resumption PSK and the server echoed back
+        // acceptance, mix it in ahead of the fresh DHE/KEM secret - the same
+        // fixed order (PSK first) the server combined them in.
+        let psk_accepted = server_hello
+            .extensions
+            .iter()
+            .any(|ext| matches!(ext, Extension::PreSharedKey { .. }));
+
+        let shared_secret = if psk_accepted {
+            let psk_secret = state.resumption_secret.ok_or_else(|| {
+                ClientError::HandshakeFailed("server accepted a PSK we never offered".to_string())
+            })?;
+            let mut combined = Vec::with_capacity(psk_secret.len() + shared_secret.len());
+            combined.extend_from_slice(&psk_secret);
+            combined.extend_from_slice(&shared_secret);
+            combined
+        } else {
+            shared_secret
+        };
 
         // 4. Parse Certificate
         let certificate_msg = HandshakeMessage::deserialize(&server_messages[1])?;
@@ -185,13 +331,60 @@ impl Client {
         handshake_messages.push(server_messages[3].clone());
 
         let server_finished: Finished = bincode::deserialize(&server_finished_msg.payload)?;
-        let expected_verify_data = compute_verify_data(&server_finished_key, &handshake_messages);
-
-        if server_finished.verify_data != expected_verify_data {
-            return Err(ClientError::FinishedVerificationFailed);
+        crate::crypto::verify_finished_data(
+            &server_finished_key,
+            &handshake_messages,
+            &server_finished.verify_data,
+        )
+        .map_err(|_| ClientError::FinishedVerificationFailed)?;
+
+        // 8. If the server asked for a client certificate, present one -
+        // signing the transcript so far, same as the server did for its own
+        // CertificateVerify.
+        let mut client_auth_messages = Vec::new();
+        if let Some(cert_request_data) = server_messages.get(4) {
+            let cert_request_msg = HandshakeMessage::deserialize(cert_request_data)?;
+            if cert_request_msg.msg_type != MessageType::CertificateRequest {
+                return Err(ClientError::InvalidMessageType);
+            }
+            handshake_messages.push(cert_request_data.clone());
+
+            let identity = self
+                .identity
+                .as_ref()
+                .ok_or(ClientError::ClientAuthRequiredButNotConfigured)?;
+
+            let client_certificate = Certificate {
+                verifying_key: identity.verifying_key.encode().to_vec(),
+            };
+            let client_certificate_payload = bincode::serialize(&client_certificate)?;
+            let client_certificate_msg =
+                HandshakeMessage::new(MessageType::Certificate, client_certificate_payload);
+            let client_certificate_data = client_certificate_msg.serialize()?;
+            handshake_messages.push(client_certificate_data.clone());
+            client_auth_messages.push(client_certificate_data);
+
+            // Sign the transcript (WITHOUT our own CertificateVerify message)
+            let mut transcript_hasher = sha3::Sha3_256::new();
+            for msg in &handshake_messages {
+                sha3::Digest::update(&mut transcript_hasher, msg);
+            }
+            let transcript_hash = sha3::Digest::finalize(transcript_hasher);
+            let signature = identity.signing_key.sign(&transcript_hash);
+            let client_cert_verify = CertificateVerify {
+                signature: signature.to_bytes().to_vec(),
+            };
+            let client_cert_verify_payload = bincode::serialize(&client_cert_verify)?;
+            let client_cert_verify_msg =
+                HandshakeMessage::new(MessageType::CertificateVerify, client_cert_verify_payload);
+            let client_cert_verify_data = client_cert_verify_msg.serialize()?;
+            // NOW add our CertificateVerify to the transcript (after signing)
+            handshake_messages.push(client_cert_verify_data.clone());
+            client_auth_messages.push(client_cert_verify_data);
         }
 
-        // 8. Send client Finished
+        // 9. Send client Finished, over the full transcript (including any
+        // client certificate messages just added above)
         let client_verify_data = compute_verify_data(&client_finished_key, &handshake_messages);
         let client_finished = Finished {
             verify_data: client_verify_data,
@@ -199,21 +392,27 @@ impl Client {
         let client_finished_payload = bincode::serialize(&client_finished)?;
         let client_finished_msg = HandshakeMessage::new(MessageType::Finished, client_finished_payload);
         let client_finished_data = client_finished_msg.serialize()?;
+        client_auth_messages.push(client_finished_data);
 
-        // 9. Create session
-        let client_key = key_schedule.derive_client_write_key();
+        // 10. Create session
+        let client_key = key_schedule.derive_client_write_key(aead_algorithm);
         let client_iv = key_schedule.derive_client_write_iv();
-        let server_key = key_schedule.derive_server_write_key();
+        let server_key = key_schedule.derive_server_write_key(aead_algorithm);
         let server_iv = key_schedule.derive_server_write_iv();
 
         let session = ClientSession {
             key_schedule,
-            write_cipher: TrafficCipher::new(&client_key, &client_iv)?,
-            read_cipher: TrafficCipher::new(&server_key, &server_iv)?,
-            client_finished_data,
+            write_cipher: TrafficCipher::new(aead_algorithm, &client_key, &client_iv)?,
+            read_cipher: TrafficCipher::new(aead_algorithm, &server_key, &server_iv)?,
+            aead_algorithm,
+            client_auth_messages,
             client_random: state.client_random,
             server_random,
             server_verifying_key,
+            peer_requested_key_update: false,
+            received_ticket: None,
+            alpn_protocol,
+            pending_fragment: Vec::new(),
         };
 
         Ok(session)
@@ -223,6 +422,12 @@ impl Client {
 /// Client handshake state
 pub struct ClientHandshakeState {
     decapsulation_key: DecapsulationKey<ml_kem::MlKem768Params>,
+    /// Ephemeral X25519 secret offered via `Extension::HybridKeyShare`,
+    /// consumed if the server negotiates the hybrid suite.
+    x25519_secret: EphemeralSecret,
+    /// Set by `start_resumption_handshake`; mixed into the key schedule if
+    /// the server echoes back acceptance of the offered PSK.
+    resumption_secret: Option<[u8; 32]>,
     client_random: [u8; 32],
     handshake_messages: Vec<Vec<u8>>,
 }
@@ -232,44 +437,145 @@ pub struct ClientSession {
     pub key_schedule: KeySchedule,
     pub write_cipher: TrafficCipher,
     pub read_cipher: TrafficCipher,
-    pub client_finished_data: Vec<u8>,
+    /// The client's final handshake flight: just `[Finished]`, or - if the
+    /// server requested a client certificate - `[Certificate,
+    /// CertificateVerify, Finished]`. Send these to the server in order;
+    /// when there are three, pass them to
+    /// `Server::verify_client_certificate`.
+    pub client_auth_messages: Vec<Vec<u8>>,
     pub client_random: [u8; 32],
     pub server_random: [u8; 32],
     pub server_verifying_key: ml_dsa::VerifyingKey<MlDsa65>,
+    /// AEAD algorithm negotiated via the cipher suite, used to size and
+    /// install fresh keys whenever a traffic secret is ratcheted.
+    aead_algorithm: AeadAlgorithm,
+    /// Set when a `KeyUpdate` was received with `update_requested = true`;
+    /// the application should call `update_traffic_keys` in response, then
+    /// clear this flag.
+    pub peer_requested_key_update: bool,
+    /// The most recently received `NewSessionTicket`, if any. Present it on
+    /// a later connection via `Client::start_resumption_handshake`, along
+    /// with `key_schedule.derive_resumption_master_secret()`.
+    pub received_ticket: Option<NewSessionTicket>,
+    /// The application protocol the server selected via ALPN, if either
+    /// side offered `Extension::Alpn`.
+    alpn_protocol: Option<Vec<u8>>,
+    /// Plaintext reassembled so far from a fragmented `ApplicationData`
+    /// message whose final fragment hasn't arrived yet.
+    pending_fragment: Vec<u8>,
 }
 
 impl ClientSession {
     /// Get the client Finished message to send to server
     pub fn get_finished_message(&self) -> &[u8] {
-        &self.client_finished_data
+        self.client_auth_messages
+            .last()
+            .expect("client_auth_messages always ends with Finished")
+    }
+
+    /// The application protocol negotiated via ALPN, if any.
+    pub fn alpn_protocol(&self) -> Option<&[u8]> {
+        self.alpn_protocol.as_deref()
     }
 
-    /// Send application data
-    pub fn send(&mut self, data: &[u8]) -> Result<Vec<u8>, ClientError> {
-        let (ciphertext, nonce) = self.write_cipher.encrypt(data)?;
+    /// The client's full final flight, in the order it must be sent. Just
+    /// `[Finished]` unless the server requested a client certificate, in
+    /// which case `[Certificate, CertificateVerify, Finished]`.
+    pub fn client_auth_messages(&self) -> &[Vec<u8>] {
+        &self.client_auth_messages
+    }
 
-        let app_data = ApplicationData {
-            ciphertext,
-            nonce,
-            tag: vec![], // Tag is included in ciphertext by AES-GCM
+    /// Send application data, splitting it into `MAX_FRAGMENT_LEN`-sized
+    /// records if it doesn't fit in one. Each record is encrypted under its
+    /// own sequence-number-derived nonce, so send the returned frames to
+    /// the peer in order.
+    pub fn send(&mut self, data: &[u8]) -> Result<Vec<Vec<u8>>, ClientError> {
+        let chunks: Vec<&[u8]> = if data.is_empty() {
+            vec![data]
+        } else {
+            data.chunks(MAX_FRAGMENT_LEN).collect()
         };
-
-        let payload = bincode::serialize(&app_data)?;
-        let msg = HandshakeMessage::new(MessageType::ApplicationData, payload);
-        Ok(msg.serialize()?)
+        let last = chunks.len() - 1;
+
+        chunks
+            .into_iter()
+            .enumerate()
+            .map(|(i, chunk)| {
+                let ciphertext = self
+                    .write_cipher
+                    .encrypt(chunk, MessageType::ApplicationData as u8)?;
+                let app_data = ApplicationData {
+                    ciphertext,
+                    more_fragments: i != last,
+                };
+
+                let payload = bincode::serialize(&app_data)?;
+                let msg = HandshakeMessage::new(MessageType::ApplicationData, payload);
+                Ok(msg.serialize()?)
+            })
+            .collect()
     }
 
-    /// Receive and decrypt application data
+    /// Receive data, decrypting `ApplicationData`, applying the read-side
+    /// key ratchet on a `KeyUpdate`, or stashing a `NewSessionTicket`
+    /// (returning no plaintext in the latter two cases). A fragmented
+    /// message is buffered across calls and only returned once its final
+    /// fragment arrives; an out-of-order or replayed fragment fails to
+    /// decrypt, since its sequence-number-derived nonce no longer matches
+    /// what `read_cipher` expects next.
     pub fn receive(&mut self, data: &[u8]) -> Result<Vec<u8>, ClientError> {
         let msg = HandshakeMessage::deserialize(data)?;
-        if msg.msg_type != MessageType::ApplicationData {
-            return Err(ClientError::InvalidMessageType);
+        match msg.msg_type {
+            MessageType::ApplicationData => {
+                let app_data: ApplicationData = bincode::deserialize(&msg.payload)?;
+                let plaintext = self
+                    .read_cipher
+                    .decrypt(&app_data.ciphertext, MessageType::ApplicationData as u8)?;
+                self.pending_fragment.extend_from_slice(&plaintext);
+                if app_data.more_fragments {
+                    Ok(Vec::new())
+                } else {
+                    Ok(std::mem::take(&mut self.pending_fragment))
+                }
+            }
+            MessageType::KeyUpdate => {
+                let key_update: KeyUpdate = bincode::deserialize(&msg.payload)?;
+                self.key_schedule.update_server_traffic_secret();
+                let server_key = self.key_schedule.derive_server_write_key(self.aead_algorithm);
+                let server_iv = self.key_schedule.derive_server_write_iv();
+                self.read_cipher.key_update(&server_key, &server_iv)?;
+                self.peer_requested_key_update = key_update.update_requested;
+                Ok(Vec::new())
+            }
+            MessageType::NewSessionTicket => {
+                let ticket: NewSessionTicket = bincode::deserialize(&msg.payload)?;
+                self.received_ticket = Some(ticket);
+                Ok(Vec::new())
+            }
+            _ => Err(ClientError::InvalidMessageType),
         }
+    }
 
-        let app_data: ApplicationData = bincode::deserialize(&msg.payload)?;
-        let plaintext = self.read_cipher.decrypt(&app_data.ciphertext, &app_data.nonce)?;
+    /// Ratchet this session's write-side (client) traffic secret forward
+    /// and install a fresh `TrafficCipher`, returning the serialized
+    /// `KeyUpdate` message to send to the server. Set `update_requested` to
+    /// ask the server to ratchet its own write side in response.
+    pub fn update_traffic_keys(&mut self, update_requested: bool) -> Result<Vec<u8>, ClientError> {
+        self.key_schedule.update_client_traffic_secret();
+        let client_key = self.key_schedule.derive_client_write_key(self.aead_algorithm);
+        let client_iv = self.key_schedule.derive_client_write_iv();
+        self.write_cipher.key_update(&client_key, &client_iv)?;
+
+        let key_update = KeyUpdate { update_requested };
+        let payload = bincode::serialize(&key_update)?;
+        let msg = HandshakeMessage::new(MessageType::KeyUpdate, payload);
+        Ok(msg.serialize()?)
+    }
 
-        Ok(plaintext)
+    /// Alias for `update_traffic_keys(false)`: ratchet this session's write
+    /// secret forward without asking the peer to do the same.
+    pub fn update_keys(&mut self) -> Result<Vec<u8>, ClientError> {
+        self.update_traffic_keys(false)
     }
 }
 
@@ -1,10 +1,15 @@
 use aes_gcm::{
-    aead::{Aead, KeyInit},
-    Aes256Gcm, Nonce,
+    aead::{Aead, KeyInit, Payload},
+    Aes128Gcm, Aes256Gcm, Nonce,
 };
+use chacha20poly1305::ChaCha20Poly1305;
+use hmac::{Hmac, Mac};
 use sha3::{Digest, Sha3_256};
+use subtle::ConstantTimeEq;
 use thiserror::Error;
 
+type HmacSha3 = Hmac<Sha3_256>;
+
 #[derive(Debug, Error)]
 pub enum CryptoError {
     #[error("Encryption failed")]
@@ -13,145 +18,494 @@ pub enum CryptoError {
     DecryptionError,
     #[error("Invalid key length")]
     InvalidKeyLength,
+    #[error("Traffic secret exhausted: sequence number would overflow")]
+    SequenceNumberExhausted,
+    #[error("Finished message verification failed")]
+    FinishedVerificationFailed,
+    #[error("Padded record length exceeds PADDED_MAX_SIZE")]
+    PaddedRecordTooLarge,
+    #[error("Declared plaintext length exceeds the decrypted buffer")]
+    InvalidPadding,
+}
+
+/// The hash a cipher suite runs the (simplified) key schedule and
+/// transcript hash over. Only one is implemented today, but keeping it as
+/// an accessor on `CipherSuite` rather than hard-coding SHA3-256 leaves room
+/// for suites to disagree on it later without another protocol-wide
+/// rewrite.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Sha3_256,
+}
+
+impl HashAlgorithm {
+    pub fn output_len(self) -> usize {
+        match self {
+            HashAlgorithm::Sha3_256 => 32,
+        }
+    }
+}
+
+/// AEAD algorithm choices for the record layer, negotiated per `CipherSuite`.
+/// ChaCha20-Poly1305 is offered alongside the AES-GCM variants for platforms
+/// without AES hardware acceleration, where it outperforms a software AES
+/// implementation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AeadAlgorithm {
+    Aes256Gcm,
+    Aes128Gcm,
+    ChaCha20Poly1305,
+}
+
+impl AeadAlgorithm {
+    pub fn key_len(self) -> usize {
+        match self {
+            AeadAlgorithm::Aes256Gcm | AeadAlgorithm::ChaCha20Poly1305 => 32,
+            AeadAlgorithm::Aes128Gcm => 16,
+        }
+    }
+
+    pub fn nonce_len(self) -> usize {
+        12
+    }
+
+    pub fn tag_len(self) -> usize {
+        16
+    }
 }
 
 /// Key schedule for deriving traffic keys
 pub struct KeySchedule {
     master_secret: [u8; 32],
+    /// Current client application traffic secret. Ratcheted forward by
+    /// `update_client_traffic_secret` on a `KeyUpdate`, so write/read keys
+    /// derived afterwards depend only on the new secret.
+    client_app_secret: [u8; 32],
+    /// Current server application traffic secret; ratcheted independently
+    /// of `client_app_secret` so updating one direction's keys doesn't
+    /// require updating the other's.
+    server_app_secret: [u8; 32],
 }
 
 impl KeySchedule {
-    /// Create a new key schedule from a shared secret
+    /// Create a new key schedule from a shared secret, chaining it through
+    /// the RFC 8446 §7.1 secret schedule:
+    /// `early_secret = Extract(0, 0)`,
+    /// `handshake_secret = Extract(Derive-Secret(early_secret, "derived", ""), shared_secret)`,
+    /// `master_secret = Extract(Derive-Secret(handshake_secret, "derived", ""), 0)`.
+    /// Application traffic secrets are then `Expand-Label(master_secret,
+    /// label, "", 32)` - we skip feeding the transcript hash into that last
+    /// step since, unlike real TLS 1.3, this handshake doesn't thread the
+    /// transcript into `KeySchedule::new` (client and server would see
+    /// different partial transcripts at this point).
     pub fn new(shared_secret: &[u8]) -> Self {
-        let mut hasher = Sha3_256::new();
-        hasher.update(shared_secret);
-        let master_secret: [u8; 32] = hasher.finalize().into();
-        
-        Self { master_secret }
+        let zero = [0u8; 32];
+
+        let early_secret = hkdf_extract(&[], &zero);
+        let derived_early = derive_secret(&early_secret, b"derived", &[]);
+
+        let handshake_secret = hkdf_extract(&derived_early, shared_secret);
+        let derived_handshake = derive_secret(&handshake_secret, b"derived", &[]);
+
+        let master_secret = hkdf_extract(&derived_handshake, &zero);
+
+        let mut client_app_secret = [0u8; 32];
+        client_app_secret
+            .copy_from_slice(&hkdf_expand_label(&master_secret, b"c ap traffic", &[], 32));
+        let mut server_app_secret = [0u8; 32];
+        server_app_secret
+            .copy_from_slice(&hkdf_expand_label(&master_secret, b"s ap traffic", &[], 32));
+
+        Self {
+            master_secret,
+            client_app_secret,
+            server_app_secret,
+        }
     }
 
-    /// Derive client write key
-    pub fn derive_client_write_key(&self) -> [u8; 32] {
-        self.hkdf_expand(b"client write key")
+    /// Derive a client write key sized for `algorithm`
+    pub fn derive_client_write_key(&self, algorithm: AeadAlgorithm) -> Vec<u8> {
+        hkdf_expand_label(&self.client_app_secret, b"key", &[], algorithm.key_len())
     }
 
-    /// Derive server write key
-    pub fn derive_server_write_key(&self) -> [u8; 32] {
-        self.hkdf_expand(b"server write key")
+    /// Derive a server write key sized for `algorithm`
+    pub fn derive_server_write_key(&self, algorithm: AeadAlgorithm) -> Vec<u8> {
+        hkdf_expand_label(&self.server_app_secret, b"key", &[], algorithm.key_len())
     }
 
     /// Derive client write IV
     pub fn derive_client_write_iv(&self) -> [u8; 12] {
-        let key = self.hkdf_expand(b"client write iv");
         let mut iv = [0u8; 12];
-        iv.copy_from_slice(&key[..12]);
+        iv.copy_from_slice(&hkdf_expand_label(&self.client_app_secret, b"iv", &[], 12));
         iv
     }
 
     /// Derive server write IV
     pub fn derive_server_write_iv(&self) -> [u8; 12] {
-        let key = self.hkdf_expand(b"server write iv");
         let mut iv = [0u8; 12];
-        iv.copy_from_slice(&key[..12]);
+        iv.copy_from_slice(&hkdf_expand_label(&self.server_app_secret, b"iv", &[], 12));
         iv
     }
 
     /// Derive finished key for handshake verification
     pub fn derive_finished_key(&self, label: &[u8]) -> [u8; 32] {
-        self.hkdf_expand(label)
+        let mut out = [0u8; 32];
+        out.copy_from_slice(&hkdf_expand_label(&self.master_secret, label, &[], 32));
+        out
+    }
+
+    /// Derive the resumption master secret, sealed into a `NewSessionTicket`
+    /// so a later connection can skip a cold ML-KEM handshake. Both sides
+    /// derive this independently from the shared `master_secret` once the
+    /// handshake completes.
+    pub fn derive_resumption_master_secret(&self) -> [u8; 32] {
+        let mut out = [0u8; 32];
+        out.copy_from_slice(&hkdf_expand_label(&self.master_secret, b"res master", &[], 32));
+        out
+    }
+
+    /// Ratchet the client application traffic secret forward:
+    /// `next = Expand-Label(cur, "traffic upd", "", 32)`. Subsequent calls
+    /// to `derive_client_write_key`/`derive_client_write_iv` derive from the
+    /// new secret.
+    pub fn update_client_traffic_secret(&mut self) {
+        self.client_app_secret
+            .copy_from_slice(&hkdf_expand_label(&self.client_app_secret, b"traffic upd", &[], 32));
     }
 
-    /// HKDF-Expand-like function using SHA3
-    fn hkdf_expand(&self, info: &[u8]) -> [u8; 32] {
-        let mut hasher = Sha3_256::new();
-        hasher.update(&self.master_secret);
-        hasher.update(info);
-        hasher.finalize().into()
+    /// Ratchet the server application traffic secret forward, mirroring
+    /// `update_client_traffic_secret`.
+    pub fn update_server_traffic_secret(&mut self) {
+        self.server_app_secret
+            .copy_from_slice(&hkdf_expand_label(&self.server_app_secret, b"traffic upd", &[], 32));
     }
 }
 
-/// Compute verify data for Finished message
+/// HKDF-Extract (RFC 5869 §2.2): `PRK = HMAC-Hash(salt, IKM)`. An empty
+/// salt is zero-filled to the hash's output length, per the RFC.
+fn hkdf_extract(salt: &[u8], ikm: &[u8]) -> [u8; 32] {
+    let zero_salt = [0u8; 32];
+    let salt = if salt.is_empty() { &zero_salt[..] } else { salt };
+    let mut mac = HmacSha3::new_from_slice(salt).expect("HMAC accepts any key length");
+    mac.update(ikm);
+    mac.finalize().into_bytes().into()
+}
+
+/// RFC 8446 §7.1 HKDF-Expand-Label: HMAC-expands `secret` over `len (u16)
+/// || "tls13 "+label (length-prefixed) || context (length-prefixed) ||
+/// 0x01` to produce `len` bytes of keying material. Every output this
+/// crate derives fits in a single HMAC-SHA3-256 block, so this skips the
+/// general multi-block HKDF-Expand counter loop.
+fn hkdf_expand_label(secret: &[u8], label: &[u8], context: &[u8], len: usize) -> Vec<u8> {
+    let mut full_label = Vec::with_capacity(6 + label.len());
+    full_label.extend_from_slice(b"tls13 ");
+    full_label.extend_from_slice(label);
+
+    let mut info = Vec::new();
+    info.extend_from_slice(&(len as u16).to_be_bytes());
+    info.push(full_label.len() as u8);
+    info.extend_from_slice(&full_label);
+    info.push(context.len() as u8);
+    info.extend_from_slice(context);
+
+    let mut mac = HmacSha3::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(&info);
+    mac.update(&[0x01]);
+    mac.finalize().into_bytes()[..len].to_vec()
+}
+
+/// `Derive-Secret(secret, label, messages) = Expand-Label(secret, label,
+/// Hash(messages), Hash.length)`.
+fn derive_secret(secret: &[u8], label: &[u8], messages: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha3_256::new();
+    hasher.update(messages);
+    let transcript_hash = hasher.finalize();
+
+    let mut out = [0u8; 32];
+    out.copy_from_slice(&hkdf_expand_label(secret, label, &transcript_hash, 32));
+    out
+}
+
+/// Compute verify data for a Finished message: true HMAC-SHA3-256 (not a
+/// bare hash) keyed by `finished_key` over the transcript hash, matching
+/// TLS 1.3's Finished MAC.
 pub fn compute_verify_data(
     finished_key: &[u8; 32],
     handshake_messages: &[Vec<u8>],
 ) -> [u8; 32] {
     let mut hasher = Sha3_256::new();
-    
-    // Hash all handshake messages
     for msg in handshake_messages {
         hasher.update(msg);
     }
     let transcript_hash = hasher.finalize();
-    
-    // HMAC-like construction
-    let mut hmac_hasher = Sha3_256::new();
-    hmac_hasher.update(finished_key);
-    hmac_hasher.update(&transcript_hash);
-    
-    hmac_hasher.finalize().into()
+
+    let mut mac = HmacSha3::new_from_slice(finished_key).expect("HMAC accepts any key length");
+    mac.update(&transcript_hash);
+    mac.finalize().into_bytes().into()
+}
+
+/// Verify a received Finished message's `verify_data` against what
+/// `compute_verify_data` computes locally, comparing in constant time so a
+/// timing side channel can't help an attacker forge the MAC byte by byte.
+pub fn verify_finished_data(
+    finished_key: &[u8; 32],
+    handshake_messages: &[Vec<u8>],
+    received_verify_data: &[u8; 32],
+) -> Result<(), CryptoError> {
+    let expected = compute_verify_data(finished_key, handshake_messages);
+    if expected.ct_eq(received_verify_data).into() {
+        Ok(())
+    } else {
+        Err(CryptoError::FinishedVerificationFailed)
+    }
+}
+
+/// One negotiated AEAD key, keyed by the algorithm the cipher suite picked.
+enum AeadKey {
+    Aes256Gcm(Aes256Gcm),
+    Aes128Gcm(Aes128Gcm),
+    ChaCha20Poly1305(ChaCha20Poly1305),
+}
+
+impl AeadKey {
+    fn new(algorithm: AeadAlgorithm, key: &[u8]) -> Result<Self, CryptoError> {
+        if key.len() != algorithm.key_len() {
+            return Err(CryptoError::InvalidKeyLength);
+        }
+        Ok(match algorithm {
+            AeadAlgorithm::Aes256Gcm => AeadKey::Aes256Gcm(Aes256Gcm::new(key.into())),
+            AeadAlgorithm::Aes128Gcm => AeadKey::Aes128Gcm(Aes128Gcm::new(key.into())),
+            AeadAlgorithm::ChaCha20Poly1305 => {
+                AeadKey::ChaCha20Poly1305(ChaCha20Poly1305::new(key.into()))
+            }
+        })
+    }
+
+    fn encrypt(&self, nonce: &Nonce, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let payload = Payload { msg: plaintext, aad };
+        match self {
+            AeadKey::Aes256Gcm(c) => c.encrypt(nonce, payload),
+            AeadKey::Aes128Gcm(c) => c.encrypt(nonce, payload),
+            AeadKey::ChaCha20Poly1305(c) => c.encrypt(nonce, payload),
+        }
+        .map_err(|_| CryptoError::EncryptionError)
+    }
+
+    fn decrypt(&self, nonce: &Nonce, ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let payload = Payload { msg: ciphertext, aad };
+        match self {
+            AeadKey::Aes256Gcm(c) => c.decrypt(nonce, payload),
+            AeadKey::Aes128Gcm(c) => c.decrypt(nonce, payload),
+            AeadKey::ChaCha20Poly1305(c) => c.decrypt(nonce, payload),
+        }
+        .map_err(|_| CryptoError::DecryptionError)
+    }
 }
 
-/// Traffic encryption state
+/// Upper bound on a padded record, independent of which `PaddingPolicy` is
+/// in effect - a bucket/power-of-two target past this is rejected rather
+/// than silently allocating an unbounded buffer.
+pub const PADDED_MAX_SIZE: usize = 64 * 1024;
+
+/// How a record's true length is hidden from an observer of ciphertext
+/// size, as traffic-analysis-resistant framing: the plaintext is always
+/// prefixed with its true length (so `decrypt` can recover it), then
+/// padded out to the policy's target size before encryption.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaddingPolicy {
+    /// No padding beyond the 4-byte length prefix; ciphertext size still
+    /// reveals the exact plaintext length.
+    None,
+    /// Round the framed (length-prefixed) size up to the next multiple of
+    /// `bucket` bytes.
+    FixedBucket(usize),
+    /// Round the framed size up to the next power of two.
+    PowerOfTwo,
+}
+
+impl PaddingPolicy {
+    fn padded_len(self, framed_len: usize) -> Result<usize, CryptoError> {
+        let target = match self {
+            PaddingPolicy::None => framed_len,
+            PaddingPolicy::FixedBucket(bucket) => {
+                let bucket = bucket.max(1);
+                framed_len.div_ceil(bucket) * bucket
+            }
+            PaddingPolicy::PowerOfTwo => framed_len.next_power_of_two(),
+        };
+        if target > PADDED_MAX_SIZE {
+            return Err(CryptoError::PaddedRecordTooLarge);
+        }
+        Ok(target)
+    }
+}
+
+/// Traffic encryption state. The nonce is never sent on the wire: both
+/// sides keep an implicit per-direction record sequence counter and derive
+/// each record's nonce from it, mirroring rustls's `MessageCipher`.
 pub struct TrafficCipher {
-    cipher: Aes256Gcm,
+    cipher: AeadKey,
+    algorithm: AeadAlgorithm,
     iv: [u8; 12],
     sequence_number: u64,
+    padding: PaddingPolicy,
 }
 
 impl TrafficCipher {
-    pub fn new(key: &[u8; 32], iv: &[u8; 12]) -> Result<Self, CryptoError> {
-        let cipher = Aes256Gcm::new(key.into());
+    pub fn new(algorithm: AeadAlgorithm, key: &[u8], iv: &[u8; 12]) -> Result<Self, CryptoError> {
+        Self::with_padding(algorithm, key, iv, PaddingPolicy::None)
+    }
+
+    /// Like `new`, but hiding the true record length behind `padding`
+    /// instead of leaking it directly in ciphertext size.
+    pub fn with_padding(
+        algorithm: AeadAlgorithm,
+        key: &[u8],
+        iv: &[u8; 12],
+        padding: PaddingPolicy,
+    ) -> Result<Self, CryptoError> {
         Ok(Self {
-            cipher,
+            cipher: AeadKey::new(algorithm, key)?,
+            algorithm,
             iv: *iv,
             sequence_number: 0,
+            padding,
         })
     }
 
-    /// Encrypt application data
-    pub fn encrypt(&mut self, plaintext: &[u8]) -> Result<(Vec<u8>, Vec<u8>), CryptoError> {
-        let nonce = self.compute_nonce();
-        let nonce_obj = Nonce::from_slice(&nonce);
-        
-        let ciphertext = self
-            .cipher
-            .encrypt(nonce_obj, plaintext)
-            .map_err(|_| CryptoError::EncryptionError)?;
-        
+    /// Encrypt application data. `content_type` is folded into the
+    /// associated data (alongside the record length) to bind the ciphertext
+    /// to its record header. The plaintext is framed with a 4-byte true
+    /// length prefix and padded per `self.padding` before encryption, so
+    /// ciphertext size reflects the padding bucket rather than the exact
+    /// plaintext length.
+    pub fn encrypt(&mut self, plaintext: &[u8], content_type: u8) -> Result<Vec<u8>, CryptoError> {
+        let framed = self.frame_with_padding(plaintext)?;
+        let nonce = self.next_nonce()?;
+        let record_len = framed.len() + self.algorithm.tag_len();
+        let aad = Self::record_aad(content_type, record_len);
+
+        let ciphertext = self.cipher.encrypt(Nonce::from_slice(&nonce), &framed, &aad)?;
         self.sequence_number += 1;
-        Ok((ciphertext, nonce.to_vec()))
+        Ok(ciphertext)
     }
 
-    /// Decrypt application data
-    pub fn decrypt(&mut self, ciphertext: &[u8], nonce: &[u8]) -> Result<Vec<u8>, CryptoError> {
-        if nonce.len() != 12 {
-            return Err(CryptoError::DecryptionError);
-        }
-        
-        let nonce_obj = Nonce::from_slice(nonce);
-        let plaintext = self
-            .cipher
-            .decrypt(nonce_obj, ciphertext)
-            .map_err(|_| CryptoError::DecryptionError)?;
-        
+    /// Decrypt application data, recomputing the same implicit nonce and
+    /// associated data the sender used for this sequence number, then
+    /// strip the length-prefix/padding frame back down to the true
+    /// plaintext. An AEAD failure here also catches a dropped or reordered
+    /// record: the sequence counter would no longer match what the sender
+    /// used.
+    pub fn decrypt(&mut self, ciphertext: &[u8], content_type: u8) -> Result<Vec<u8>, CryptoError> {
+        let nonce = self.next_nonce()?;
+        let aad = Self::record_aad(content_type, ciphertext.len());
+
+        let plaintext = self.cipher.decrypt(Nonce::from_slice(&nonce), ciphertext, &aad)?;
         self.sequence_number += 1;
-        Ok(plaintext)
+        Self::unframe(&plaintext)
+    }
+
+    /// Prepend `plaintext`'s true length (big-endian `u32`) and pad the
+    /// result out to `self.padding`'s target size with zero bytes.
+    fn frame_with_padding(&self, plaintext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let true_len = plaintext.len();
+        let padded_len = self.padding.padded_len(4 + true_len)?;
+
+        let mut framed = Vec::with_capacity(padded_len);
+        framed.extend_from_slice(&(true_len as u32).to_be_bytes());
+        framed.extend_from_slice(plaintext);
+        framed.resize(padded_len, 0);
+        Ok(framed)
+    }
+
+    /// Inverse of `frame_with_padding`: read the true-length prefix and
+    /// truncate away the padding, rejecting a declared length that doesn't
+    /// fit in what was actually decrypted.
+    fn unframe(framed: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        if framed.len() < 4 {
+            return Err(CryptoError::InvalidPadding);
+        }
+        let (len_bytes, rest) = framed.split_at(4);
+        let true_len = u32::from_be_bytes(
+            len_bytes.try_into().expect("split_at(4) yields a 4-byte slice"),
+        ) as usize;
+
+        if true_len > rest.len() {
+            return Err(CryptoError::InvalidPadding);
+        }
+        Ok(rest[..true_len].to_vec())
+    }
+
+    /// Compute this record's nonce from the current sequence number,
+    /// erroring instead of wrapping once the 64-bit counter is exhausted -
+    /// reusing a nonce under the same key would break AEAD security.
+    fn next_nonce(&self) -> Result<[u8; 12], CryptoError> {
+        if self.sequence_number == u64::MAX {
+            return Err(CryptoError::SequenceNumberExhausted);
+        }
+        Ok(self.compute_nonce())
+    }
+
+    /// Re-key in place: install a fresh AEAD key/IV pair (typically derived
+    /// via `KeySchedule::update_client_traffic_secret`/
+    /// `update_server_traffic_secret` followed by the matching
+    /// `derive_*_write_key`/`derive_*_write_iv` calls) and reset the
+    /// sequence counter to 0, matching TLS 1.3's KeyUpdate.
+    pub fn key_update(&mut self, key: &[u8], iv: &[u8; 12]) -> Result<(), CryptoError> {
+        self.cipher = AeadKey::new(self.algorithm, key)?;
+        self.iv = *iv;
+        self.sequence_number = 0;
+        Ok(())
+    }
+
+    /// Associated data binding a record to its header: content type
+    /// followed by the big-endian ciphertext length.
+    fn record_aad(content_type: u8, record_len: usize) -> [u8; 5] {
+        let mut aad = [0u8; 5];
+        aad[0] = content_type;
+        aad[1..].copy_from_slice(&(record_len as u32).to_be_bytes());
+        aad
     }
 
     /// Compute nonce by XORing IV with sequence number
     fn compute_nonce(&self) -> [u8; 12] {
         let mut nonce = self.iv;
         let seq_bytes = self.sequence_number.to_be_bytes();
-        
+
         // XOR the last 8 bytes with the sequence number
         for i in 0..8 {
             nonce[4 + i] ^= seq_bytes[i];
         }
-        
+
         nonce
     }
 }
 
+/// Seal a session ticket's plaintext (the resumption master secret) under a
+/// server-held ticket key with AES-256-GCM and a freshly generated 12-byte
+/// nonce, which is prepended to the returned blob so `open_ticket` doesn't
+/// need it passed separately.
+pub fn seal_ticket(ticket_key: &[u8; 32], plaintext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let nonce = random_bytes::<12>();
+    let cipher = AeadKey::new(AeadAlgorithm::Aes256Gcm, ticket_key)?;
+    let ciphertext = cipher.encrypt(Nonce::from_slice(&nonce), plaintext, &[])?;
+
+    let mut sealed = Vec::with_capacity(nonce.len() + ciphertext.len());
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+/// Recover a session ticket's plaintext, reversing `seal_ticket`.
+pub fn open_ticket(ticket_key: &[u8; 32], sealed: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    if sealed.len() < 12 {
+        return Err(CryptoError::DecryptionError);
+    }
+    let (nonce, ciphertext) = sealed.split_at(12);
+    let cipher = AeadKey::new(AeadAlgorithm::Aes256Gcm, ticket_key)?;
+    cipher.decrypt(Nonce::from_slice(nonce), ciphertext, &[])
+}
+
 /// Generate random bytes
 pub fn random_bytes<const N: usize>() -> [u8; N] {
     let mut bytes = [0u8; N];
@@ -168,10 +522,10 @@ mod tests {
     fn test_key_derivation() {
         let shared_secret = b"shared_secret_for_testing";
         let schedule = KeySchedule::new(shared_secret);
-        
-        let client_key = schedule.derive_client_write_key();
-        let server_key = schedule.derive_server_write_key();
-        
+
+        let client_key = schedule.derive_client_write_key(AeadAlgorithm::Aes256Gcm);
+        let server_key = schedule.derive_server_write_key(AeadAlgorithm::Aes256Gcm);
+
         // Keys should be different
         assert_ne!(client_key, server_key);
     }
@@ -180,16 +534,131 @@ mod tests {
     fn test_encryption_decryption() {
         let key = random_bytes::<32>();
         let iv = random_bytes::<12>();
-        
-        let mut cipher = TrafficCipher::new(&key, &iv).unwrap();
+
+        let mut cipher = TrafficCipher::new(AeadAlgorithm::Aes256Gcm, &key, &iv).unwrap();
         let plaintext = b"Hello, PQ-TLS!";
-        
-        let (ciphertext, nonce) = cipher.encrypt(plaintext).unwrap();
-        
-        // Create new cipher with same key/IV
-        let mut decipher = TrafficCipher::new(&key, &iv).unwrap();
-        let decrypted = decipher.decrypt(&ciphertext, &nonce).unwrap();
-        
+
+        let ciphertext = cipher.encrypt(plaintext, 23).unwrap();
+
+        // Create new cipher with same key/IV, in lockstep on sequence number
+        let mut decipher = TrafficCipher::new(AeadAlgorithm::Aes256Gcm, &key, &iv).unwrap();
+        let decrypted = decipher.decrypt(&ciphertext, 23).unwrap();
+
         assert_eq!(plaintext.as_slice(), decrypted.as_slice());
     }
+
+    #[test]
+    fn test_decrypt_fails_on_replayed_record() {
+        let key = random_bytes::<32>();
+        let iv = random_bytes::<12>();
+
+        let mut cipher = TrafficCipher::new(AeadAlgorithm::Aes256Gcm, &key, &iv).unwrap();
+        let first = cipher.encrypt(b"first", 23).unwrap();
+        let _second = cipher.encrypt(b"second", 23).unwrap();
+
+        // Decrypting "first" against a cipher that has never advanced skips
+        // nothing, but trying to decrypt it a second time (as if a record
+        // were replayed, leaving the counter one ahead of the sender) must
+        // fail rather than silently return stale plaintext.
+        let mut decipher = TrafficCipher::new(AeadAlgorithm::Aes256Gcm, &key, &iv).unwrap();
+        decipher.decrypt(&first, 23).unwrap();
+        assert!(decipher.decrypt(&first, 23).is_err());
+    }
+
+    #[test]
+    fn test_chacha20poly1305_encryption_decryption() {
+        let key = random_bytes::<32>();
+        let iv = random_bytes::<12>();
+
+        let mut cipher = TrafficCipher::new(AeadAlgorithm::ChaCha20Poly1305, &key, &iv).unwrap();
+        let plaintext = b"Hello, PQ-TLS!";
+
+        let ciphertext = cipher.encrypt(plaintext, 23).unwrap();
+
+        let mut decipher = TrafficCipher::new(AeadAlgorithm::ChaCha20Poly1305, &key, &iv).unwrap();
+        let decrypted = decipher.decrypt(&ciphertext, 23).unwrap();
+
+        assert_eq!(plaintext.as_slice(), decrypted.as_slice());
+    }
+
+    #[test]
+    fn test_fixed_bucket_padding_hides_true_length() {
+        let key = random_bytes::<32>();
+        let iv = random_bytes::<12>();
+
+        let mut cipher =
+            TrafficCipher::with_padding(AeadAlgorithm::Aes256Gcm, &key, &iv, PaddingPolicy::FixedBucket(64))
+                .unwrap();
+        let mut decipher =
+            TrafficCipher::with_padding(AeadAlgorithm::Aes256Gcm, &key, &iv, PaddingPolicy::FixedBucket(64))
+                .unwrap();
+
+        let short = cipher.encrypt(b"hi", 23).unwrap();
+        let longer = cipher.encrypt(b"a slightly longer message", 23).unwrap();
+
+        // Both plaintexts land in the same 64-byte bucket, so the
+        // ciphertexts (bucket size + tag) come out the same length even
+        // though the plaintexts don't.
+        assert_eq!(short.len(), longer.len());
+
+        assert_eq!(decipher.decrypt(&short, 23).unwrap(), b"hi");
+        assert_eq!(decipher.decrypt(&longer, 23).unwrap(), b"a slightly longer message");
+    }
+
+    #[test]
+    fn test_power_of_two_padding_round_trips() {
+        let key = random_bytes::<32>();
+        let iv = random_bytes::<12>();
+
+        let mut cipher =
+            TrafficCipher::with_padding(AeadAlgorithm::Aes256Gcm, &key, &iv, PaddingPolicy::PowerOfTwo).unwrap();
+        let mut decipher =
+            TrafficCipher::with_padding(AeadAlgorithm::Aes256Gcm, &key, &iv, PaddingPolicy::PowerOfTwo).unwrap();
+
+        let plaintext = vec![0x42u8; 100];
+        let ciphertext = cipher.encrypt(&plaintext, 23).unwrap();
+
+        // 4-byte prefix + 100 bytes = 104, rounded up to 128.
+        assert_eq!(ciphertext.len(), 128 + AeadAlgorithm::Aes256Gcm.tag_len());
+        assert_eq!(decipher.decrypt(&ciphertext, 23).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn test_padding_target_over_max_size_rejected() {
+        let key = random_bytes::<32>();
+        let iv = random_bytes::<12>();
+
+        let mut cipher =
+            TrafficCipher::with_padding(AeadAlgorithm::Aes256Gcm, &key, &iv, PaddingPolicy::FixedBucket(1))
+                .unwrap();
+        let oversized = vec![0u8; PADDED_MAX_SIZE + 1];
+        assert!(matches!(
+            cipher.encrypt(&oversized, 23),
+            Err(CryptoError::PaddedRecordTooLarge)
+        ));
+    }
+
+    #[test]
+    fn test_aes128gcm_requires_16_byte_key() {
+        let key = random_bytes::<32>();
+        let iv = random_bytes::<12>();
+
+        let result = TrafficCipher::new(AeadAlgorithm::Aes128Gcm, &key, &iv);
+        assert!(matches!(result, Err(CryptoError::InvalidKeyLength)));
+    }
+
+    #[test]
+    fn test_seal_and_open_ticket() {
+        let ticket_key = random_bytes::<32>();
+        let schedule = KeySchedule::new(b"shared_secret_for_testing");
+        let resumption_secret = schedule.derive_resumption_master_secret();
+
+        let sealed = seal_ticket(&ticket_key, &resumption_secret).unwrap();
+        let recovered = open_ticket(&ticket_key, &sealed).unwrap();
+        assert_eq!(resumption_secret.as_slice(), recovered.as_slice());
+
+        // A ticket sealed under a different key must not open.
+        let other_key = random_bytes::<32>();
+        assert!(open_ticket(&other_key, &sealed).is_err());
+    }
 }
@@ -1,16 +1,19 @@
 //! # PQ-TLS: Post-Quantum TLS Implementation
 //!
 //! A TLS-like protocol implementation using post-quantum cryptographic primitives:
-//! - **ML-KEM (Kyber)** for key encapsulation
+//! - **ML-KEM (Kyber)** for key encapsulation, optionally combined with an
+//!   X25519 ECDH share for hybrid classical+PQ key exchange
 //! - **ML-DSA (Dilithium)** for digital signatures
-//! - **AES-256-GCM** for symmetric encryption
+//! - **AES-256-GCM, AES-128-GCM or ChaCha20-Poly1305** for symmetric
+//!   encryption, negotiated per `CipherSuite`
 //!
 //! ## Features
 //!
-//! - Post-quantum secure key exchange using ML-KEM-768
+//! - Post-quantum secure key exchange using ML-KEM-768, with an optional
+//!   X25519 hybrid mode for defense in depth against a single broken primitive
 //! - Server authentication using ML-DSA-65 signatures
 //! - Forward secrecy through ephemeral key exchange
-//! - Authenticated encryption with AES-256-GCM
+//! - Negotiable AEAD cipher suites (AES-256-GCM, AES-128-GCM, ChaCha20-Poly1305)
 //!
 //! ## Example
 //!
@@ -36,7 +39,7 @@
 //!
 //! // Now both parties can exchange encrypted messages
 //! let encrypted = client_session.send(b"Hello, PQ-TLS!").unwrap();
-//! let decrypted = server_session.receive(&encrypted).unwrap();
+//! let decrypted = server_session.receive(&encrypted[0]).unwrap();
 //! ```
 
 pub mod client;
@@ -45,10 +48,13 @@ pub mod crypto;
 pub mod protocol;
 pub mod server;
 
-pub use client::{Client, ClientConfig, ClientError, ClientSession};
+pub use client::{Client, ClientConfig, ClientError, ClientIdentity, ClientSession};
 pub use config::SecurityLevel;
 pub use protocol::{CipherSuite, PQ_TLS_VERSION};
-pub use server::{Server, ServerConfig, ServerError, ServerSession};
+pub use server::{
+    AllowAnyAuthenticatedClient, ClientCertVerifier, RequireKnownClientKeys, Server, ServerConfig,
+    ServerError, ServerSession,
+};
 
 #[cfg(test)]
 mod integration_tests {
@@ -76,13 +82,13 @@ mod integration_tests {
         // Test encrypted communication
         let message = b"Hello from client!";
         let encrypted = client_session.send(message).unwrap();
-        let decrypted = server_session.receive(&encrypted).unwrap();
+        let decrypted = server_session.receive(&encrypted[0]).unwrap();
         assert_eq!(message.as_slice(), decrypted.as_slice());
 
         // Test reverse direction
         let response = b"Hello from server!";
         let encrypted_response = server_session.send(response).unwrap();
-        let decrypted_response = client_session.receive(&encrypted_response).unwrap();
+        let decrypted_response = client_session.receive(&encrypted_response[0]).unwrap();
         assert_eq!(response.as_slice(), decrypted_response.as_slice());
     }
 
@@ -101,8 +107,247 @@ mod integration_tests {
         for i in 0..5 {
             let msg = format!("Message {}", i);
             let encrypted = client_session.send(msg.as_bytes()).unwrap();
-            let decrypted = server_session.receive(&encrypted).unwrap();
+            let decrypted = server_session.receive(&encrypted[0]).unwrap();
             assert_eq!(msg.as_bytes(), decrypted.as_slice());
         }
     }
+
+    #[test]
+    fn test_mutual_authentication() {
+        use ml_dsa::{KeyGen, MlDsa65};
+
+        let server_config = ServerConfig {
+            client_cert_verifier: Some(Box::new(AllowAnyAuthenticatedClient)),
+            ..ServerConfig::default()
+        };
+        let server = Server::new(server_config);
+
+        let client_keypair = MlDsa65::key_gen(&mut rand::rngs::OsRng);
+        let client = Client::with_identity(
+            ClientConfig::default(),
+            ClientIdentity {
+                signing_key: client_keypair.signing_key().clone(),
+                verifying_key: client_keypair.verifying_key().clone(),
+            },
+        );
+
+        let (client_state, client_hello) = client.start_handshake().unwrap();
+        let mut server_session = server.handshake(&client_hello).unwrap();
+
+        let client_session = client
+            .complete_handshake(client_state, server_session.handshake_messages())
+            .unwrap();
+
+        assert_eq!(client_session.client_auth_messages().len(), 3);
+        server
+            .verify_client_certificate(&mut server_session, client_session.client_auth_messages())
+            .unwrap();
+        assert!(server_session.authenticated_client_key.is_some());
+    }
+
+    #[test]
+    fn test_post_handshake_key_update() {
+        let server = Server::new(ServerConfig::default());
+        let client = Client::new(ClientConfig::default());
+
+        let (client_state, client_hello) = client.start_handshake().unwrap();
+        let mut server_session = server.handshake(&client_hello).unwrap();
+        let mut client_session = client
+            .complete_handshake(client_state, server_session.handshake_messages())
+            .unwrap();
+
+        // Server ratchets its write side and tells the client to do the same.
+        let key_update = server_session.update_traffic_keys(true).unwrap();
+        let consumed = client_session.receive(&key_update).unwrap();
+        assert!(consumed.is_empty());
+        assert!(client_session.peer_requested_key_update);
+
+        // Client responds by ratcheting its own write side.
+        let response = client_session.update_traffic_keys(false).unwrap();
+        let consumed = server_session.receive(&response).unwrap();
+        assert!(consumed.is_empty());
+
+        // Both directions still work, now under the new keys.
+        let message = b"post-update message";
+        let encrypted = client_session.send(message).unwrap();
+        let decrypted = server_session.receive(&encrypted[0]).unwrap();
+        assert_eq!(message.as_slice(), decrypted.as_slice());
+
+        let response_msg = b"post-update reply";
+        let encrypted_response = server_session.send(response_msg).unwrap();
+        let decrypted_response = client_session.receive(&encrypted_response[0]).unwrap();
+        assert_eq!(response_msg.as_slice(), decrypted_response.as_slice());
+    }
+
+    #[test]
+    fn test_handshake_with_chacha20poly1305_suite() {
+        let suite = CipherSuite::MlKem768MlDsa65ChaCha20Poly1305;
+        let server = Server::new(ServerConfig {
+            cipher_suites: vec![suite],
+            ..ServerConfig::default()
+        });
+        let client = Client::new(ClientConfig {
+            cipher_suites: vec![suite],
+            ..ClientConfig::default()
+        });
+
+        let (client_state, client_hello) = client.start_handshake().unwrap();
+        let mut server_session = server.handshake(&client_hello).unwrap();
+        let mut client_session = client
+            .complete_handshake(client_state, server_session.handshake_messages())
+            .unwrap();
+
+        let message = b"hello over chacha20poly1305";
+        let encrypted = client_session.send(message).unwrap();
+        let decrypted = server_session.receive(&encrypted[0]).unwrap();
+        assert_eq!(message.as_slice(), decrypted.as_slice());
+    }
+
+    #[test]
+    fn test_handshake_with_hybrid_x25519_mlkem_suite() {
+        let suite = CipherSuite::X25519MlKem768MlDsa65Aes256Gcm;
+        let server = Server::new(ServerConfig {
+            cipher_suites: vec![suite],
+            ..ServerConfig::default()
+        });
+        let client = Client::new(ClientConfig {
+            cipher_suites: vec![suite],
+            ..ClientConfig::default()
+        });
+
+        let (client_state, client_hello) = client.start_handshake().unwrap();
+        let mut server_session = server.handshake(&client_hello).unwrap();
+        let mut client_session = client
+            .complete_handshake(client_state, server_session.handshake_messages())
+            .unwrap();
+
+        let message = b"hello over the hybrid key exchange";
+        let encrypted = client_session.send(message).unwrap();
+        let decrypted = server_session.receive(&encrypted[0]).unwrap();
+        assert_eq!(message.as_slice(), decrypted.as_slice());
+    }
+
+    #[test]
+    fn test_session_resumption_via_psk_ticket() {
+        let server = Server::new(ServerConfig::default());
+        let client = Client::new(ClientConfig::default());
+
+        // First, full handshake.
+        let (client_state, client_hello) = client.start_handshake().unwrap();
+        let mut server_session = server.handshake(&client_hello).unwrap();
+        let mut client_session = client
+            .complete_handshake(client_state, server_session.handshake_messages())
+            .unwrap();
+
+        // Server mints a ticket and sends it to the client.
+        let ticket_message = server.issue_session_ticket(&server_session).unwrap();
+        client_session.receive(&ticket_message).unwrap();
+        let ticket = client_session
+            .received_ticket
+            .clone()
+            .expect("ticket should have been stashed");
+        let resumption_secret = client_session
+            .key_schedule
+            .derive_resumption_master_secret();
+
+        // Second connection, offering the ticket for resumption.
+        let (resumed_state, resumed_hello) = client
+            .start_resumption_handshake(ticket.ticket, resumption_secret)
+            .unwrap();
+        let mut resumed_server_session = server.handshake(&resumed_hello).unwrap();
+        let mut resumed_client_session = client
+            .complete_handshake(resumed_state, resumed_server_session.handshake_messages())
+            .unwrap();
+
+        let message = b"resumed session traffic";
+        let encrypted = resumed_client_session.send(message).unwrap();
+        let decrypted = resumed_server_session.receive(&encrypted[0]).unwrap();
+        assert_eq!(message.as_slice(), decrypted.as_slice());
+    }
+
+    #[test]
+    fn test_fragmented_message_reassembly() {
+        let server = Server::new(ServerConfig::default());
+        let client = Client::new(ClientConfig::default());
+
+        let (client_state, client_hello) = client.start_handshake().unwrap();
+        let mut server_session = server.handshake(&client_hello).unwrap();
+        let mut client_session = client
+            .complete_handshake(client_state, server_session.handshake_messages())
+            .unwrap();
+
+        let message: Vec<u8> = (0..(protocol::MAX_FRAGMENT_LEN * 2 + 37))
+            .map(|i| (i % 256) as u8)
+            .collect();
+
+        let frames = client_session.send(&message).unwrap();
+        assert_eq!(frames.len(), 3, "expected three fragments for this message size");
+
+        let mut reassembled = Vec::new();
+        for frame in &frames {
+            let plaintext = server_session.receive(frame).unwrap();
+            reassembled.extend_from_slice(&plaintext);
+        }
+        assert_eq!(reassembled, message);
+    }
+
+    #[test]
+    fn test_alpn_negotiation_selects_mutual_protocol() {
+        let server_config = ServerConfig {
+            alpn_protocols: vec![b"h2".to_vec(), b"http/1.1".to_vec()],
+            ..ServerConfig::default()
+        };
+        let server = Server::new(server_config);
+
+        let client_config = ClientConfig {
+            alpn_protocols: vec![b"http/1.1".to_vec(), b"h2".to_vec()],
+            ..ClientConfig::default()
+        };
+        let client = Client::new(client_config);
+
+        let (client_state, client_hello) = client.start_handshake().unwrap();
+        let server_session = server.handshake(&client_hello).unwrap();
+        let client_session = client
+            .complete_handshake(client_state, server_session.handshake_messages())
+            .unwrap();
+
+        assert_eq!(client_session.alpn_protocol(), Some(b"h2".as_slice()));
+    }
+
+    #[test]
+    fn test_alpn_negotiation_fails_with_no_overlap() {
+        let server_config = ServerConfig {
+            alpn_protocols: vec![b"h2".to_vec()],
+            ..ServerConfig::default()
+        };
+        let server = Server::new(server_config);
+
+        let client_config = ClientConfig {
+            alpn_protocols: vec![b"spdy/3".to_vec()],
+            ..ClientConfig::default()
+        };
+        let client = Client::new(client_config);
+
+        let (client_state, client_hello) = client.start_handshake().unwrap();
+        let server_session = server.handshake(&client_hello).unwrap();
+        let result = client.complete_handshake(client_state, server_session.handshake_messages());
+
+        assert!(matches!(result, Err(ClientError::NoAlpnOverlap)));
+    }
+
+    #[test]
+    fn test_mutual_authentication_requires_client_identity() {
+        let server_config = ServerConfig {
+            client_cert_verifier: Some(Box::new(AllowAnyAuthenticatedClient)),
+            ..ServerConfig::default()
+        };
+        let server = Server::new(server_config);
+        let client = Client::new(ClientConfig::default());
+
+        let (client_state, client_hello) = client.start_handshake().unwrap();
+        let server_session = server.handshake(&client_hello).unwrap();
+
+        let result = client.complete_handshake(client_state, server_session.handshake_messages());
+        assert!(result.is_err());
+    }
 }
@@ -31,16 +31,16 @@ fn main() {
 
     let msg1 = b"Hello from client!";
     let encrypted1 = client_session.send(msg1).unwrap();
-    let decrypted1 = server_session.receive(&encrypted1).unwrap();
+    let decrypted1 = server_session.receive(&encrypted1[0]).unwrap();
     println!("Client → Server: \"{}\"", String::from_utf8_lossy(msg1));
-    println!("Ciphertext: {} bytes", encrypted1.len());
+    println!("Ciphertext: {} bytes", encrypted1[0].len());
     println!("Decrypted:  \"{}\" \n", String::from_utf8_lossy(&decrypted1));
 
     let msg2 = b"Hello from server!";
     let encrypted2 = server_session.send(msg2).unwrap();
-    let decrypted2 = client_session.receive(&encrypted2).unwrap();
+    let decrypted2 = client_session.receive(&encrypted2[0]).unwrap();
     println!("Server → Client: \"{}\"", String::from_utf8_lossy(msg2));
-    println!("Ciphertext: {} bytes", encrypted2.len());
+    println!("Ciphertext: {} bytes", encrypted2[0].len());
     println!("Decrypted:  \"{}\" \n", String::from_utf8_lossy(&decrypted2));
 
     println!("PQ-TLS handshake and communication successful!");
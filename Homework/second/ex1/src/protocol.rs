@@ -1,17 +1,27 @@
 use serde::{Deserialize, Serialize};
 
+use crate::crypto::{AeadAlgorithm, HashAlgorithm};
+
 /// Protocol version identifier
 pub const PQ_TLS_VERSION: u16 = 0x0304; // TLS 1.3 with PQ
 
+/// Maximum plaintext bytes carried by a single `ApplicationData` record.
+/// Buffers larger than this are split across multiple records by
+/// `ClientSession::send`/`ServerSession::send` and reassembled on receipt.
+pub const MAX_FRAGMENT_LEN: usize = 16 * 1024;
+
 /// Message types in the PQ-TLS handshake
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum MessageType {
     ClientHello = 1,
     ServerHello = 2,
     Certificate = 11,
+    CertificateRequest = 13,
     CertificateVerify = 15,
     Finished = 20,
     ApplicationData = 23,
+    KeyUpdate = 24,
+    NewSessionTicket = 25,
 }
 
 /// Client Hello message
@@ -44,6 +54,11 @@ pub struct CertificateVerify {
     pub signature: Vec<u8>,
 }
 
+/// Certificate Request message: the server asks the client to authenticate
+/// with its own certificate (mutual authentication)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CertificateRequest {}
+
 /// Finished message with HMAC
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Finished {
@@ -59,6 +74,16 @@ pub enum CipherSuite {
     MlKem768MlDsa65Aes256Gcm = 0x0002,
     /// ML-KEM-1024 for key exchange + ML-DSA-87 for signatures + AES-256-GCM for encryption (NIST Level 5)
     MlKem1024MlDsa87Aes256Gcm = 0x0003,
+    /// ML-KEM-768 for key exchange + ML-DSA-65 for signatures + ChaCha20-Poly1305 for encryption
+    /// (NIST Level 3) - favored on platforms without AES hardware support
+    MlKem768MlDsa65ChaCha20Poly1305 = 0x0004,
+    /// ML-KEM-768 for key exchange + ML-DSA-65 for signatures + AES-128-GCM for encryption
+    /// (NIST Level 3, lighter-weight symmetric cipher)
+    MlKem768MlDsa65Aes128Gcm = 0x0005,
+    /// X25519 ECDH combined with ML-KEM-768 for key exchange (hybrid, secure
+    /// if either primitive holds) + ML-DSA-65 for signatures + AES-256-GCM
+    /// for encryption (NIST Level 3)
+    X25519MlKem768MlDsa65Aes256Gcm = 0x0006,
 }
 
 impl CipherSuite {
@@ -67,6 +92,9 @@ impl CipherSuite {
             CipherSuite::MlKem512MlDsa44Aes256Gcm => "ML-KEM-512_ML-DSA-44_AES-256-GCM",
             CipherSuite::MlKem768MlDsa65Aes256Gcm => "ML-KEM-768_ML-DSA-65_AES-256-GCM",
             CipherSuite::MlKem1024MlDsa87Aes256Gcm => "ML-KEM-1024_ML-DSA-87_AES-256-GCM",
+            CipherSuite::MlKem768MlDsa65ChaCha20Poly1305 => "ML-KEM-768_ML-DSA-65_CHACHA20-POLY1305",
+            CipherSuite::MlKem768MlDsa65Aes128Gcm => "ML-KEM-768_ML-DSA-65_AES-128-GCM",
+            CipherSuite::X25519MlKem768MlDsa65Aes256Gcm => "X25519_ML-KEM-768_ML-DSA-65_AES-256-GCM",
         }
     }
 
@@ -75,8 +103,34 @@ impl CipherSuite {
             CipherSuite::MlKem512MlDsa44Aes256Gcm => 1,
             CipherSuite::MlKem768MlDsa65Aes256Gcm => 3,
             CipherSuite::MlKem1024MlDsa87Aes256Gcm => 5,
+            CipherSuite::MlKem768MlDsa65ChaCha20Poly1305 => 3,
+            CipherSuite::MlKem768MlDsa65Aes128Gcm => 3,
+            CipherSuite::X25519MlKem768MlDsa65Aes256Gcm => 3,
+        }
+    }
+
+    /// Whether this suite negotiates the hybrid X25519+ML-KEM-768 key
+    /// exchange (`Extension::HybridKeyShare`) instead of plain ML-KEM.
+    pub fn is_hybrid_key_exchange(&self) -> bool {
+        matches!(self, CipherSuite::X25519MlKem768MlDsa65Aes256Gcm)
+    }
+
+    /// The AEAD algorithm this suite negotiates for the record layer
+    pub fn aead_algorithm(&self) -> AeadAlgorithm {
+        match self {
+            CipherSuite::MlKem512MlDsa44Aes256Gcm
+            | CipherSuite::MlKem768MlDsa65Aes256Gcm
+            | CipherSuite::MlKem1024MlDsa87Aes256Gcm => AeadAlgorithm::Aes256Gcm,
+            CipherSuite::MlKem768MlDsa65ChaCha20Poly1305 => AeadAlgorithm::ChaCha20Poly1305,
+            CipherSuite::MlKem768MlDsa65Aes128Gcm => AeadAlgorithm::Aes128Gcm,
+            CipherSuite::X25519MlKem768MlDsa65Aes256Gcm => AeadAlgorithm::Aes256Gcm,
         }
     }
+
+    /// The hash this suite runs the key schedule and transcript hash over
+    pub fn hash(&self) -> HashAlgorithm {
+        HashAlgorithm::Sha3_256
+    }
 }
 
 /// TLS extensions
@@ -86,10 +140,37 @@ pub enum Extension {
     KeyShare { encapsulation_key: Vec<u8> },
     /// Contains the ML-KEM ciphertext
     KeyShareCiphertext { ciphertext: Vec<u8> },
+    /// Hybrid classical+PQ key share: an X25519 public key alongside the
+    /// ML-KEM encapsulation key, for `CipherSuite::X25519MlKem768MlDsa65Aes256Gcm`.
+    /// The combined hybrid secret is always
+    /// `x25519_shared_secret || mlkem_shared_secret` (classical first, then
+    /// PQ), so both sides must concatenate in that order. When a resumption
+    /// PSK is accepted, it is prepended to this before `KeySchedule::new`.
+    HybridKeyShare {
+        x25519_public: [u8; 32],
+        encapsulation_key: Vec<u8>,
+    },
+    /// The server's half of the hybrid key share: its X25519 public key
+    /// alongside the ML-KEM ciphertext encapsulated to the client's key.
+    HybridKeyShareCiphertext {
+        x25519_public: [u8; 32],
+        ciphertext: Vec<u8>,
+    },
     /// Server name indication
     ServerName { hostname: String },
     /// Supported versions
     SupportedVersions { versions: Vec<u16> },
+    /// Offers (in a `ClientHello`) or accepts (echoed in a `ServerHello`) a
+    /// resumption PSK from a prior session's `NewSessionTicket`. `identity`
+    /// is the opaque sealed ticket blob; the server echoes this extension
+    /// with an empty `identity` to signal acceptance.
+    PreSharedKey { identity: Vec<u8>, obfuscated_age: u32 },
+    /// Application protocols the client is willing to speak, in preference
+    /// order, for ALPN negotiation.
+    Alpn { protocols: Vec<Vec<u8>> },
+    /// The single protocol the server selected from the client's `Alpn`
+    /// list, echoed back in the `ServerHello`.
+    AlpnSelected { protocol: Vec<u8> },
 }
 
 /// Handshake message wrapper
@@ -113,10 +194,41 @@ impl HandshakeMessage {
     }
 }
 
-/// Application data record
+/// Application data record. The nonce is never sent on the wire - both
+/// sides derive it from an implicit per-direction sequence counter kept in
+/// `TrafficCipher` - and the tag is already part of `ciphertext` (AEAD
+/// appends it to the output). A buffer larger than `MAX_FRAGMENT_LEN`
+/// becomes a sequence of these records, each individually encrypted under
+/// its own sequence-number-derived nonce; `more_fragments` tells the
+/// receiver whether to keep buffering or that it has the whole message.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ApplicationData {
     pub ciphertext: Vec<u8>,
-    pub nonce: Vec<u8>,
-    pub tag: Vec<u8>,
+    pub more_fragments: bool,
+}
+
+/// Post-handshake key update, for rekeying a direction's traffic secret
+/// without a full handshake
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyUpdate {
+    /// Mirrors TLS 1.3's `update_requested`: if set, the peer must respond
+    /// with its own `KeyUpdate` once it has ratcheted its write side.
+    pub update_requested: bool,
+}
+
+/// Post-handshake resumption ticket, for skipping a cold ML-KEM handshake
+/// on a later connection via `Extension::PreSharedKey`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewSessionTicket {
+    /// The session's resumption master secret, sealed under the server's
+    /// ticket key. Opaque to the client - presented back verbatim as
+    /// `Extension::PreSharedKey::identity`.
+    pub ticket: Vec<u8>,
+    /// How long the ticket may be used to resume, in seconds. Advisory:
+    /// `Server::handshake` does not check a presented ticket's age.
+    pub lifetime_seconds: u32,
+    /// Random value in the role of TLS 1.3's `ticket_age_add`. This client
+    /// does not track ticket age and always presents an `obfuscated_age`
+    /// of 0, so the value is not used.
+    pub age_add: u32,
 }
@@ -1,11 +1,12 @@
-use crate::crypto::{compute_verify_data, random_bytes, KeySchedule, TrafficCipher};
+use crate::crypto::{compute_verify_data, random_bytes, AeadAlgorithm, KeySchedule, TrafficCipher};
 use crate::protocol::*;
 use ml_dsa::{KeyGen, MlDsa65};
 use ml_kem::{kem::{Encapsulate, EncapsulationKey}, Encoded, EncodedSizeUser};
 use rand::rngs::OsRng;
 use sha3::Digest;
-use signature::{Signer, SignatureEncoding};
+use signature::{Signer, SignatureEncoding, Verifier};
 use thiserror::Error;
+use x25519_dalek::{EphemeralSecret, PublicKey as X25519PublicKey};
 
 #[derive(Debug, Error)]
 pub enum ServerError {
@@ -29,20 +30,79 @@ pub struct Server {
     verifying_key: ml_dsa::VerifyingKey<MlDsa65>,
     /// Server configuration
     config: ServerConfig,
+    /// Seals/opens resumption tickets minted by `issue_session_ticket`.
+    /// Never leaves this process, so tickets can't be forged or decrypted
+    /// by anyone else.
+    ticket_key: [u8; 32],
 }
 
 pub struct ServerConfig {
     pub cipher_suites: Vec<CipherSuite>,
+    /// When set, `handshake` requests a client certificate and the caller
+    /// must follow up with `Server::verify_client_certificate` before
+    /// trusting the session. `None` means the server never asks for one.
+    pub client_cert_verifier: Option<Box<dyn ClientCertVerifier>>,
+    /// Application protocols this server supports, in preference order, for
+    /// ALPN negotiation. If the client offers `Extension::Alpn` and none of
+    /// its protocols appear here, the handshake proceeds without a
+    /// selection and the client is expected to treat that as an error.
+    pub alpn_protocols: Vec<Vec<u8>>,
 }
 
 impl Default for ServerConfig {
     fn default() -> Self {
         Self {
             cipher_suites: vec![CipherSuite::MlKem768MlDsa65Aes256Gcm],
+            client_cert_verifier: None,
+            alpn_protocols: Vec::new(),
         }
     }
 }
 
+/// Decides whether a client certificate presented during mutual
+/// authentication should be trusted, mirroring rustls's
+/// `ClientCertVerifier`: the server doesn't hard-code a trust policy, it
+/// delegates the decision to whatever is configured.
+pub trait ClientCertVerifier: Send + Sync {
+    /// Return `true` if `verifying_key` should be trusted to authenticate a
+    /// client. `Server::verify_client_certificate` calls this as soon as it
+    /// has decoded the client's certificate, before it checks the
+    /// `CertificateVerify` signature; this is purely a trust decision, not a
+    /// cryptographic one.
+    fn verify(&self, verifying_key: &ml_dsa::VerifyingKey<MlDsa65>) -> bool;
+}
+
+/// Trusts any client certificate whose signature verifies - the client is
+/// authenticated as *someone*, but which client it is isn't restricted to an
+/// allowlist.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AllowAnyAuthenticatedClient;
+
+impl ClientCertVerifier for AllowAnyAuthenticatedClient {
+    fn verify(&self, _verifying_key: &ml_dsa::VerifyingKey<MlDsa65>) -> bool {
+        true
+    }
+}
+
+/// Only trusts client certificates whose verifying key is in a fixed
+/// allowlist, encoded the same way `Certificate::verifying_key` is.
+pub struct RequireKnownClientKeys {
+    allowed_keys: Vec<Vec<u8>>,
+}
+
+impl RequireKnownClientKeys {
+    pub fn new(allowed_keys: Vec<Vec<u8>>) -> Self {
+        Self { allowed_keys }
+    }
+}
+
+impl ClientCertVerifier for RequireKnownClientKeys {
+    fn verify(&self, verifying_key: &ml_dsa::VerifyingKey<MlDsa65>) -> bool {
+        let encoded = verifying_key.encode().to_vec();
+        self.allowed_keys.iter().any(|key| key == &encoded)
+    }
+}
+
 impl Server {
     /// Create a new server with generated ML-DSA keys
     pub fn new(config: ServerConfig) -> Self {
@@ -53,6 +113,7 @@ impl Server {
             signing_key: keypair.signing_key().clone(),
             verifying_key: keypair.verifying_key().clone(),
             config,
+            ticket_key: random_bytes::<32>(),
         }
     }
 
@@ -66,9 +127,33 @@ impl Server {
             signing_key,
             verifying_key,
             config,
+            ticket_key: random_bytes::<32>(),
         }
     }
 
+    /// How long a minted resumption ticket remains valid.
+    const TICKET_LIFETIME_SECONDS: u32 = 7 * 24 * 3600;
+
+    /// After a full handshake, mint a resumption ticket the client can
+    /// present on a later connection (via `Client::start_resumption_handshake`)
+    /// to skip a cold ML-KEM handshake. Seals `session`'s resumption master
+    /// secret under this server's ticket key - the client must derive the
+    /// same secret independently (`session.key_schedule.derive_resumption_master_secret()`)
+    /// and hold onto it alongside the returned ticket bytes.
+    pub fn issue_session_ticket(&self, session: &ServerSession) -> Result<Vec<u8>, ServerError> {
+        let resumption_secret = session.key_schedule.derive_resumption_master_secret();
+        let sealed = crate::crypto::seal_ticket(&self.ticket_key, &resumption_secret)?;
+
+        let ticket = NewSessionTicket {
+            ticket: sealed,
+            lifetime_seconds: Self::TICKET_LIFETIME_SECONDS,
+            age_add: u32::from_le_bytes(random_bytes::<4>()),
+        };
+        let payload = bincode::serialize(&ticket)?;
+        let msg = HandshakeMessage::new(MessageType::NewSessionTicket, payload);
+        Ok(msg.serialize()?)
+    }
+
     /// Handle the TLS handshake and establish a secure session
     pub fn handshake(&self, client_hello_data: &[u8]) -> Result<ServerSession, ServerError> {
         let mut handshake_messages = Vec::new();
@@ -91,37 +176,133 @@ impl Server {
             .find(|cs| client_hello.cipher_suites.contains(cs))
             .ok_or_else(|| ServerError::HandshakeFailed("No common cipher suite".to_string()))?;
 
-        // 3. Extract client's ML-KEM encapsulation key
-        let client_kem_ek = client_hello
+        // 3-5. Extract the client's key share(s), perform key establishment,
+        // and build the matching ServerHello extension. Plain ML-KEM, or -
+        // if the hybrid suite was negotiated - ML-KEM combined with an
+        // ephemeral X25519 ECDH against the client's public.
+        let mut rng = OsRng;
+        let (shared_secret, server_hello_extension) = if cipher_suite.is_hybrid_key_exchange() {
+            let (client_x25519_public, client_kem_ek) = client_hello
+                .extensions
+                .iter()
+                .find_map(|ext| match ext {
+                    Extension::HybridKeyShare { x25519_public, encapsulation_key } => {
+                        Some((*x25519_public, encapsulation_key.clone()))
+                    }
+                    _ => None,
+                })
+                .ok_or_else(|| ServerError::HandshakeFailed("No hybrid key share".to_string()))?;
+
+            let client_ek_encoded: Encoded<EncapsulationKey<ml_kem::MlKem768Params>> =
+                client_kem_ek.as_slice().try_into()
+                .map_err(|_| ServerError::HandshakeFailed("Invalid encapsulation key".to_string()))?;
+            let client_ek = EncapsulationKey::<ml_kem::MlKem768Params>::from_bytes(&client_ek_encoded);
+            let (ciphertext, mlkem_ss) = client_ek
+                .encapsulate(&mut rng)
+                .map_err(|_| ServerError::HandshakeFailed("Encapsulation failed".to_string()))?;
+
+            let server_x25519_secret = EphemeralSecret::random_from_rng(&mut rng);
+            let server_x25519_public = X25519PublicKey::from(&server_x25519_secret);
+            let x25519_ss = server_x25519_secret
+                .diffie_hellman(&X25519PublicKey::from(client_x25519_public));
+
+            // Fixed concatenation order: classical first, then PQ.
+            let mut combined = Vec::with_capacity(32 + mlkem_ss.as_ref().len());
+            combined.extend_from_slice(x25519_ss.as_bytes());
+            combined.extend_from_slice(mlkem_ss.as_ref());
+
+            let extension = Extension::HybridKeyShareCiphertext {
+                x25519_public: server_x25519_public.to_bytes(),
+                ciphertext: ciphertext.to_vec(),
+            };
+            (combined, extension)
+        } else {
+            let client_kem_ek = client_hello
+                .extensions
+                .iter()
+                .find_map(|ext| match ext {
+                    Extension::KeyShare { encapsulation_key } => Some(encapsulation_key.clone()),
+                    _ => None,
+                })
+                .ok_or_else(|| ServerError::HandshakeFailed("No key share".to_string()))?;
+
+            let client_ek_encoded: Encoded<EncapsulationKey<ml_kem::MlKem768Params>> =
+                client_kem_ek.as_slice().try_into()
+                .map_err(|_| ServerError::HandshakeFailed("Invalid encapsulation key".to_string()))?;
+
+            let client_ek = EncapsulationKey::<ml_kem::MlKem768Params>::from_bytes(&client_ek_encoded);
+            let (ciphertext, mlkem_ss) = client_ek
+                .encapsulate(&mut rng)
+                .map_err(|_| ServerError::HandshakeFailed("Encapsulation failed".to_string()))?;
+
+            let extension = Extension::KeyShareCiphertext {
+                ciphertext: ciphertext.to_vec(),
+            };
+            (mlkem_ss.as_ref().to_vec(), extension)
+        };
+
+        // 5b. If the client offered a resumption PSK and it decrypts under
+        // this server's ticket key, mix the recovered resumption secret
+        // into the shared secret (PSK first, then the fresh DHE/KEM
+        // secret) for forward secrecy, and echo the extension back to
+        // signal acceptance. A ticket that does not open under the ticket
+        // key is ignored rather than rejected, falling back transparently
+        // to a cold handshake; the ticket's age and lifetime are not checked.
+        let psk_accepted = client_hello
             .extensions
             .iter()
             .find_map(|ext| match ext {
-                Extension::KeyShare { encapsulation_key } => Some(encapsulation_key.clone()),
+                Extension::PreSharedKey { identity, .. } => Some(identity.clone()),
                 _ => None,
             })
-            .ok_or_else(|| ServerError::HandshakeFailed("No key share".to_string()))?;
+            .and_then(|ticket| crate::crypto::open_ticket(&self.ticket_key, &ticket).ok());
 
-        // 4. Perform ML-KEM encapsulation
-        let client_ek_encoded: Encoded<EncapsulationKey<ml_kem::MlKem768Params>> = 
-            client_kem_ek.as_slice().try_into()
-            .map_err(|_| ServerError::HandshakeFailed("Invalid encapsulation key".to_string()))?;
-        
-        let client_ek = EncapsulationKey::<ml_kem::MlKem768Params>::from_bytes(&client_ek_encoded);
-        
-        let mut rng = OsRng;
-        let (ciphertext, shared_secret) = client_ek
-            .encapsulate(&mut rng)
-            .map_err(|_| ServerError::HandshakeFailed("Encapsulation failed".to_string()))?;
+        let shared_secret = if let Some(psk_secret) = &psk_accepted {
+            let mut combined = Vec::with_capacity(psk_secret.len() + shared_secret.len());
+            combined.extend_from_slice(psk_secret);
+            combined.extend_from_slice(&shared_secret);
+            combined
+        } else {
+            shared_secret
+        };
+
+        // 5c. Select an ALPN protocol, if the client offered any - the same
+        // server-preference pattern as cipher suite selection above. No
+        // match simply omits the extension; the client treats that as a
+        // hard failure if it required ALPN.
+        let client_alpn_protocols = client_hello
+            .extensions
+            .iter()
+            .find_map(|ext| match ext {
+                Extension::Alpn { protocols } => Some(protocols.clone()),
+                _ => None,
+            })
+            .unwrap_or_default();
+
+        let selected_alpn_protocol = self
+            .config
+            .alpn_protocols
+            .iter()
+            .find(|p| client_alpn_protocols.contains(p))
+            .cloned();
 
-        // 5. Create ServerHello
+        // 5d. Create ServerHello
         let server_random = random_bytes::<32>();
+        let mut server_hello_extensions = vec![server_hello_extension];
+        if psk_accepted.is_some() {
+            server_hello_extensions.push(Extension::PreSharedKey {
+                identity: Vec::new(),
+                obfuscated_age: 0,
+            });
+        }
+        if let Some(protocol) = selected_alpn_protocol {
+            server_hello_extensions.push(Extension::AlpnSelected { protocol });
+        }
         let server_hello = ServerHello {
             version: PQ_TLS_VERSION,
             random: server_random,
             cipher_suite: *cipher_suite,
-            extensions: vec![Extension::KeyShareCiphertext {
-                ciphertext: ciphertext.to_vec(),
-            }],
+            extensions: server_hello_extensions,
         };
 
         let server_hello_payload = bincode::serialize(&server_hello)?;
@@ -167,28 +348,135 @@ impl Server {
         let finished_data = finished_msg.serialize()?;
         handshake_messages.push(finished_data.clone());
 
-        // 10. Create session
-        let server_key = key_schedule.derive_server_write_key();
+        // 10. Optionally request a client certificate for mutual authentication
+        let mut outgoing_messages = vec![
+            server_hello_data,
+            certificate_data,
+            cert_verify_data,
+            finished_data,
+        ];
+        if self.config.client_cert_verifier.is_some() {
+            let cert_request_payload = bincode::serialize(&CertificateRequest {})?;
+            let cert_request_msg =
+                HandshakeMessage::new(MessageType::CertificateRequest, cert_request_payload);
+            let cert_request_data = cert_request_msg.serialize()?;
+            handshake_messages.push(cert_request_data.clone());
+            outgoing_messages.push(cert_request_data);
+        }
+
+        // 11. Create session
+        let aead_algorithm = cipher_suite.aead_algorithm();
+        let server_key = key_schedule.derive_server_write_key(aead_algorithm);
         let server_iv = key_schedule.derive_server_write_iv();
-        let client_key = key_schedule.derive_client_write_key();
+        let client_key = key_schedule.derive_client_write_key(aead_algorithm);
         let client_iv = key_schedule.derive_client_write_iv();
 
         let session = ServerSession {
             key_schedule,
-            write_cipher: TrafficCipher::new(&server_key, &server_iv)?,
-            read_cipher: TrafficCipher::new(&client_key, &client_iv)?,
-            handshake_messages: vec![
-                server_hello_data,
-                certificate_data,
-                cert_verify_data,
-                finished_data,
-            ],
+            write_cipher: TrafficCipher::new(aead_algorithm, &server_key, &server_iv)?,
+            read_cipher: TrafficCipher::new(aead_algorithm, &client_key, &client_iv)?,
+            aead_algorithm,
+            handshake_messages: outgoing_messages,
+            transcript: handshake_messages,
             client_random,
             server_random,
+            authenticated_client_key: None,
+            peer_requested_key_update: false,
+            pending_fragment: Vec::new(),
         };
 
         Ok(session)
     }
+
+    /// After `handshake` requested a client certificate (`ServerConfig`'s
+    /// `client_cert_verifier` was set), verify the client's follow-up
+    /// `Certificate` + `CertificateVerify` + `Finished` messages (in that
+    /// order, as returned by `ClientSession::client_auth_messages`) and
+    /// record the authenticated client key on `session`.
+    pub fn verify_client_certificate(
+        &self,
+        session: &mut ServerSession,
+        client_messages: &[Vec<u8>],
+    ) -> Result<(), ServerError> {
+        let verifier = self.config.client_cert_verifier.as_ref().ok_or_else(|| {
+            ServerError::HandshakeFailed("client certificates were not requested".to_string())
+        })?;
+
+        if client_messages.len() != 3 {
+            return Err(ServerError::HandshakeFailed(
+                "expected client Certificate, CertificateVerify and Finished".to_string(),
+            ));
+        }
+
+        let mut transcript = session.transcript.clone();
+
+        // 1. Parse client Certificate
+        let certificate_msg = HandshakeMessage::deserialize(&client_messages[0])?;
+        if certificate_msg.msg_type != MessageType::Certificate {
+            return Err(ServerError::InvalidMessageType);
+        }
+        transcript.push(client_messages[0].clone());
+
+        let certificate: Certificate = bincode::deserialize(&certificate_msg.payload)?;
+        let encoded_vk: ml_dsa::EncodedVerifyingKey<MlDsa65> = certificate
+            .verifying_key
+            .as_slice()
+            .try_into()
+            .map_err(|_| ServerError::HandshakeFailed("Invalid verifying key format".to_string()))?;
+        let client_verifying_key = ml_dsa::VerifyingKey::<MlDsa65>::decode(&encoded_vk);
+
+        if !verifier.verify(&client_verifying_key) {
+            return Err(ServerError::SignatureVerificationFailed);
+        }
+
+        // 2. Parse and verify client CertificateVerify
+        let cert_verify_msg = HandshakeMessage::deserialize(&client_messages[1])?;
+        if cert_verify_msg.msg_type != MessageType::CertificateVerify {
+            return Err(ServerError::InvalidMessageType);
+        }
+        let cert_verify: CertificateVerify = bincode::deserialize(&cert_verify_msg.payload)?;
+
+        // Verify signature over transcript (WITHOUT client CertificateVerify message)
+        let mut transcript_hasher = sha3::Sha3_256::new();
+        for msg in &transcript {
+            sha3::Digest::update(&mut transcript_hasher, msg);
+        }
+        let transcript_hash = sha3::Digest::finalize(transcript_hasher);
+
+        let encoded_sig: ml_dsa::EncodedSignature<MlDsa65> = cert_verify
+            .signature
+            .as_slice()
+            .try_into()
+            .map_err(|_| ServerError::HandshakeFailed("Invalid signature format".to_string()))?;
+        let signature = ml_dsa::Signature::<MlDsa65>::decode(&encoded_sig)
+            .ok_or_else(|| ServerError::HandshakeFailed("Signature decode failed".to_string()))?;
+
+        client_verifying_key
+            .verify(&transcript_hash, &signature)
+            .map_err(|_| ServerError::SignatureVerificationFailed)?;
+
+        // NOW add CertificateVerify to transcript (after verification)
+        transcript.push(client_messages[1].clone());
+
+        // 3. Parse and verify client Finished
+        let client_finished_msg = HandshakeMessage::deserialize(&client_messages[2])?;
+        if client_finished_msg.msg_type != MessageType::Finished {
+            return Err(ServerError::InvalidMessageType);
+        }
+        transcript.push(client_messages[2].clone());
+
+        let client_finished_key = session.key_schedule.derive_finished_key(b"client finished");
+        let client_finished: Finished = bincode::deserialize(&client_finished_msg.payload)?;
+        crate::crypto::verify_finished_data(
+            &client_finished_key,
+            &transcript,
+            &client_finished.verify_data,
+        )
+        .map_err(|_| ServerError::SignatureVerificationFailed)?;
+
+        session.authenticated_client_key = Some(client_verifying_key);
+        Ok(())
+    }
 }
 
 /// Active server session after handshake
@@ -197,37 +485,114 @@ pub struct ServerSession {
     pub write_cipher: TrafficCipher,
     pub read_cipher: TrafficCipher,
     pub handshake_messages: Vec<Vec<u8>>,
+    /// Full transcript so far (including `ClientHello`), kept around so
+    /// `Server::verify_client_certificate` can extend it with the client's
+    /// certificate messages rather than recomputing it from scratch.
+    transcript: Vec<Vec<u8>>,
+    /// AEAD algorithm negotiated via the cipher suite, used to size and
+    /// install fresh keys whenever a traffic secret is ratcheted.
+    aead_algorithm: AeadAlgorithm,
     pub client_random: [u8; 32],
     pub server_random: [u8; 32],
+    /// The client's ML-DSA verifying key, once `verify_client_certificate`
+    /// has accepted its certificate. Applications can check this to make
+    /// authorization decisions; `None` until mutual authentication succeeds.
+    pub authenticated_client_key: Option<ml_dsa::VerifyingKey<MlDsa65>>,
+    /// Set when a `KeyUpdate` was received with `update_requested = true`;
+    /// the application should call `update_traffic_keys` in response, then
+    /// clear this flag.
+    pub peer_requested_key_update: bool,
+    /// Plaintext reassembled so far from a fragmented `ApplicationData`
+    /// message whose final fragment hasn't arrived yet.
+    pending_fragment: Vec<u8>,
 }
 
 impl ServerSession {
-    /// Send application data
-    pub fn send(&mut self, data: &[u8]) -> Result<Vec<u8>, ServerError> {
-        let (ciphertext, nonce) = self.write_cipher.encrypt(data)?;
-        
-        let app_data = ApplicationData {
-            ciphertext,
-            nonce,
-            tag: vec![], // Tag is included in ciphertext by AES-GCM
+    /// Send application data, splitting it into `MAX_FRAGMENT_LEN`-sized
+    /// records if it doesn't fit in one. Each record is encrypted under its
+    /// own sequence-number-derived nonce, so send the returned frames to
+    /// the peer in order.
+    pub fn send(&mut self, data: &[u8]) -> Result<Vec<Vec<u8>>, ServerError> {
+        let chunks: Vec<&[u8]> = if data.is_empty() {
+            vec![data]
+        } else {
+            data.chunks(MAX_FRAGMENT_LEN).collect()
         };
-        
-        let payload = bincode::serialize(&app_data)?;
-        let msg = HandshakeMessage::new(MessageType::ApplicationData, payload);
-        Ok(msg.serialize()?)
+        let last = chunks.len() - 1;
+
+        chunks
+            .into_iter()
+            .enumerate()
+            .map(|(i, chunk)| {
+                let ciphertext = self
+                    .write_cipher
+                    .encrypt(chunk, MessageType::ApplicationData as u8)?;
+                let app_data = ApplicationData {
+                    ciphertext,
+                    more_fragments: i != last,
+                };
+
+                let payload = bincode::serialize(&app_data)?;
+                let msg = HandshakeMessage::new(MessageType::ApplicationData, payload);
+                Ok(msg.serialize()?)
+            })
+            .collect()
     }
 
-    /// Receive and decrypt application data
+    /// Receive data, decrypting `ApplicationData` or applying the read-side
+    /// key ratchet on a `KeyUpdate` (returning no plaintext in that case). A
+    /// fragmented message is buffered across calls and only returned once
+    /// its final fragment arrives; an out-of-order or replayed fragment
+    /// fails to decrypt, since its sequence-number-derived nonce no longer
+    /// matches what `read_cipher` expects next.
     pub fn receive(&mut self, data: &[u8]) -> Result<Vec<u8>, ServerError> {
         let msg = HandshakeMessage::deserialize(data)?;
-        if msg.msg_type != MessageType::ApplicationData {
-            return Err(ServerError::InvalidMessageType);
+        match msg.msg_type {
+            MessageType::ApplicationData => {
+                let app_data: ApplicationData = bincode::deserialize(&msg.payload)?;
+                let plaintext = self
+                    .read_cipher
+                    .decrypt(&app_data.ciphertext, MessageType::ApplicationData as u8)?;
+                self.pending_fragment.extend_from_slice(&plaintext);
+                if app_data.more_fragments {
+                    Ok(Vec::new())
+                } else {
+                    Ok(std::mem::take(&mut self.pending_fragment))
+                }
+            }
+            MessageType::KeyUpdate => {
+                let key_update: KeyUpdate = bincode::deserialize(&msg.payload)?;
+                self.key_schedule.update_client_traffic_secret();
+                let client_key = self.key_schedule.derive_client_write_key(self.aead_algorithm);
+                let client_iv = self.key_schedule.derive_client_write_iv();
+                self.read_cipher.key_update(&client_key, &client_iv)?;
+                self.peer_requested_key_update = key_update.update_requested;
+                Ok(Vec::new())
+            }
+            _ => Err(ServerError::InvalidMessageType),
         }
+    }
 
-        let app_data: ApplicationData = bincode::deserialize(&msg.payload)?;
-        let plaintext = self.read_cipher.decrypt(&app_data.ciphertext, &app_data.nonce)?;
-        
-        Ok(plaintext)
+    /// Ratchet this session's write-side (server) traffic secret forward
+    /// and install a fresh `TrafficCipher`, returning the serialized
+    /// `KeyUpdate` message to send to the client. Set `update_requested` to
+    /// ask the client to ratchet its own write side in response.
+    pub fn update_traffic_keys(&mut self, update_requested: bool) -> Result<Vec<u8>, ServerError> {
+        self.key_schedule.update_server_traffic_secret();
+        let server_key = self.key_schedule.derive_server_write_key(self.aead_algorithm);
+        let server_iv = self.key_schedule.derive_server_write_iv();
+        self.write_cipher.key_update(&server_key, &server_iv)?;
+
+        let key_update = KeyUpdate { update_requested };
+        let payload = bincode::serialize(&key_update)?;
+        let msg = HandshakeMessage::new(MessageType::KeyUpdate, payload);
+        Ok(msg.serialize()?)
+    }
+
+    /// Alias for `update_traffic_keys(false)`: ratchet this session's write
+    /// secret forward without asking the peer to do the same.
+    pub fn update_keys(&mut self) -> Result<Vec<u8>, ServerError> {
+        self.update_traffic_keys(false)
     }
 
     /// Get handshake messages to send to client
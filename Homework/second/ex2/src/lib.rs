@@ -4,11 +4,15 @@ use pqcrypto_traits::kem::{Ciphertext, PublicKey, SharedSecret};
 use rand::rngs::OsRng;
 use rand::RngCore;
 use sha2::{Digest, Sha256};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
 use aes_gcm::{
     aead::{Aead, AeadCore, KeyInit},
     Aes256Gcm, Nonce,
 };
 
+type HmacSha256 = Hmac<Sha256>;
+
 // Protocol version
 const KEM_TLS_VERSION: u16 = 0x0304;
 
@@ -18,32 +22,79 @@ const SERVER_HELLO: u8 = 2;
 const FINISHED: u8 = 20;
 const APPLICATION_DATA: u8 = 23;
 
-/// Derives keys from a shared secret using HKDF-like construction
-fn derive_keys(shared_secret: &[u8], context: &[u8]) -> ([u8; 32], [u8; 32]) {
-    let mut hasher = Sha256::new();
-    hasher.update(b"KEM-TLS-1.3");
-    hasher.update(shared_secret);
-    hasher.update(context);
-    let master = hasher.finalize();
-
-    // Derive client write key
-    let mut client_hasher = Sha256::new();
-    client_hasher.update(&master);
-    client_hasher.update(b"client write key");
-    let client_key = client_hasher.finalize();
-
-    // Derive server write key
-    let mut server_hasher = Sha256::new();
-    server_hasher.update(&master);
-    server_hasher.update(b"server write key");
-    let server_key = server_hasher.finalize();
-
-    let mut ck = [0u8; 32];
-    let mut sk = [0u8; 32];
-    ck.copy_from_slice(&client_key);
-    sk.copy_from_slice(&server_key);
-
-    (ck, sk)
+/// HKDF-Expand-Label, as in RFC 8446 §7.1: HMAC-expands `secret` over
+/// `len_be16 || "tls13 "+label (length-prefixed) || transcript_hash
+/// (length-prefixed) || 0x01` to produce `len` bytes of keying material.
+/// Every output we derive here fits in a single HMAC-SHA256 block, so this
+/// skips the general multi-block HKDF-Expand counter loop.
+fn expand_label(secret: &[u8], label: &[u8], transcript_hash: &[u8], len: usize) -> Vec<u8> {
+    let mut full_label = Vec::with_capacity(6 + label.len());
+    full_label.extend_from_slice(b"tls13 ");
+    full_label.extend_from_slice(label);
+
+    let mut info = Vec::new();
+    info.extend_from_slice(&(len as u16).to_be_bytes());
+    info.push(full_label.len() as u8);
+    info.extend_from_slice(&full_label);
+    info.push(transcript_hash.len() as u8);
+    info.extend_from_slice(transcript_hash);
+
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(&info);
+    mac.update(&[0x01]);
+    mac.finalize().into_bytes()[..len].to_vec()
+}
+
+/// Per-direction key material derived from the handshake secret: an AEAD
+/// key, a record IV, and a Finished-message MAC key, each independently
+/// expanded so that compromising one doesn't reveal the others.
+struct DirectionalKeys {
+    aead_key: [u8; 32],
+    iv: [u8; 12],
+    finished_key: [u8; 32],
+}
+
+impl DirectionalKeys {
+    fn derive(handshake_secret: &[u8], direction: &str, transcript_hash: &[u8]) -> Self {
+        let mut aead_key = [0u8; 32];
+        aead_key.copy_from_slice(&expand_label(
+            handshake_secret,
+            format!("{direction} write key").as_bytes(),
+            transcript_hash,
+            32,
+        ));
+
+        let mut iv = [0u8; 12];
+        iv.copy_from_slice(&expand_label(
+            handshake_secret,
+            format!("{direction} write iv").as_bytes(),
+            transcript_hash,
+            12,
+        ));
+
+        let mut finished_key = [0u8; 32];
+        finished_key.copy_from_slice(&expand_label(
+            handshake_secret,
+            format!("{direction} finished").as_bytes(),
+            transcript_hash,
+            32,
+        ));
+
+        Self { aead_key, iv, finished_key }
+    }
+}
+
+/// Derives the client and server traffic keys from the Kyber shared
+/// secret: an HKDF-Extract over the shared secret produces a handshake
+/// secret, from which every per-direction output is expanded via
+/// `expand_label`. Replaces the old ad-hoc `SHA256(secret || context)`
+/// cascade, which gave no domain separation between outputs.
+fn derive_traffic_keys(shared_secret: &[u8], transcript_hash: &[u8]) -> (DirectionalKeys, DirectionalKeys) {
+    let (handshake_secret, _) = Hkdf::<Sha256>::extract(None, shared_secret);
+
+    let client_keys = DirectionalKeys::derive(&handshake_secret, "client", transcript_hash);
+    let server_keys = DirectionalKeys::derive(&handshake_secret, "server", transcript_hash);
+    (client_keys, server_keys)
 }
 
 /// Represents a handshake message
@@ -82,9 +133,13 @@ pub struct KemTlsClient {
     public_key: kyber768::PublicKey,
     client_random: [u8; 32],
     server_random: Option<[u8; 32]>,
-    shared_secret: Option<Vec<u8>>,
+    client_finished_key: Option<[u8; 32]>,
     client_cipher: Option<Aes256Gcm>,
     server_cipher: Option<Aes256Gcm>,
+    /// Derived per the key schedule alongside `client_cipher`/`server_cipher`,
+    /// for parity with the full RFC 8446 record-key output set.
+    client_iv: Option<[u8; 12]>,
+    server_iv: Option<[u8; 12]>,
     handshake_messages: Vec<u8>,
 }
 
@@ -92,7 +147,7 @@ impl KemTlsClient {
     pub fn new() -> Self {
         // Generate KEM keypair
         let (pk, sk) = kyber768::keypair();
-        
+
         // Generate random nonce
         let mut client_random = [0u8; 32];
         OsRng.fill_bytes(&mut client_random);
@@ -102,9 +157,11 @@ impl KemTlsClient {
             public_key: pk,
             client_random,
             server_random: None,
-            shared_secret: None,
+            client_finished_key: None,
             client_cipher: None,
             server_cipher: None,
+            client_iv: None,
+            server_iv: None,
             handshake_messages: Vec::new(),
         }
     }
@@ -166,17 +223,17 @@ impl KemTlsClient {
             .map_err(|_| "Invalid ciphertext".to_string())?;
         
         let shared_secret = kyber768::decapsulate(&ct, &self.secret_key);
-        
-        // Derive keys
-        let mut context = Vec::new();
-        context.extend_from_slice(&self.client_random);
-        context.extend_from_slice(&server_random);
-        
-        let (client_key, server_key) = derive_keys(shared_secret.as_bytes(), &context);
-        
-        self.shared_secret = Some(shared_secret.as_bytes().to_vec());
-        self.client_cipher = Some(Aes256Gcm::new(&client_key.into()));
-        self.server_cipher = Some(Aes256Gcm::new(&server_key.into()));
+
+        // Derive keys: HKDF-Extract over the shared secret, then
+        // HKDF-Expand-Label (with an empty context, as RFC 8446 does for
+        // key/IV/Finished-key expansion) for each per-direction output.
+        let (client_keys, server_keys) = derive_traffic_keys(shared_secret.as_bytes(), &[]);
+
+        self.client_finished_key = Some(client_keys.finished_key);
+        self.client_cipher = Some(Aes256Gcm::new(&client_keys.aead_key.into()));
+        self.server_cipher = Some(Aes256Gcm::new(&server_keys.aead_key.into()));
+        self.client_iv = Some(client_keys.iv);
+        self.server_iv = Some(server_keys.iv);
 
         Ok(())
     }
@@ -187,11 +244,14 @@ impl KemTlsClient {
         hasher.update(&self.handshake_messages);
         let handshake_hash = hasher.finalize();
 
-        let mut verify_hasher = Sha256::new();
-        verify_hasher.update(self.shared_secret.as_ref().unwrap());
-        verify_hasher.update(b"client finished");
-        verify_hasher.update(&handshake_hash);
-        let verify_data = verify_hasher.finalize();
+        let finished_key = self
+            .client_finished_key
+            .as_ref()
+            .ok_or("Keys not derived yet".to_string())?;
+        let mut mac =
+            HmacSha256::new_from_slice(finished_key).expect("HMAC accepts any key length");
+        mac.update(&handshake_hash);
+        let verify_data = mac.finalize().into_bytes();
 
         let msg = HandshakeMessage {
             msg_type: FINISHED,
@@ -258,9 +318,13 @@ impl Default for KemTlsClient {
 pub struct KemTlsServer {
     server_random: [u8; 32],
     client_random: Option<[u8; 32]>,
-    shared_secret: Option<Vec<u8>>,
+    client_finished_key: Option<[u8; 32]>,
     client_cipher: Option<Aes256Gcm>,
     server_cipher: Option<Aes256Gcm>,
+    /// Derived per the key schedule alongside `client_cipher`/`server_cipher`,
+    /// for parity with the full RFC 8446 record-key output set.
+    client_iv: Option<[u8; 12]>,
+    server_iv: Option<[u8; 12]>,
     handshake_messages: Vec<u8>,
 }
 
@@ -272,9 +336,11 @@ impl KemTlsServer {
         KemTlsServer {
             server_random,
             client_random: None,
-            shared_secret: None,
+            client_finished_key: None,
             client_cipher: None,
             server_cipher: None,
+            client_iv: None,
+            server_iv: None,
             handshake_messages: Vec::new(),
         }
     }
@@ -312,16 +378,16 @@ impl KemTlsServer {
         // Encapsulate to generate shared secret
         let (shared_secret, ciphertext) = kyber768::encapsulate(&public_key);
 
-        // Derive keys
-        let mut context = Vec::new();
-        context.extend_from_slice(&client_random);
-        context.extend_from_slice(&self.server_random);
-        
-        let (client_key, server_key) = derive_keys(shared_secret.as_bytes(), &context);
-        
-        self.shared_secret = Some(shared_secret.as_bytes().to_vec());
-        self.client_cipher = Some(Aes256Gcm::new(&client_key.into()));
-        self.server_cipher = Some(Aes256Gcm::new(&server_key.into()));
+        // Derive keys: HKDF-Extract over the shared secret, then
+        // HKDF-Expand-Label (with an empty context, as RFC 8446 does for
+        // key/IV/Finished-key expansion) for each per-direction output.
+        let (client_keys, server_keys) = derive_traffic_keys(shared_secret.as_bytes(), &[]);
+
+        self.client_finished_key = Some(client_keys.finished_key);
+        self.client_cipher = Some(Aes256Gcm::new(&client_keys.aead_key.into()));
+        self.server_cipher = Some(Aes256Gcm::new(&server_keys.aead_key.into()));
+        self.client_iv = Some(client_keys.iv);
+        self.server_iv = Some(server_keys.iv);
 
         // Build ServerHello
         let mut sh_payload = Vec::new();
@@ -357,15 +423,15 @@ impl KemTlsServer {
         hasher.update(hash_data);
         let handshake_hash = hasher.finalize();
 
-        let mut verify_hasher = Sha256::new();
-        verify_hasher.update(self.shared_secret.as_ref().unwrap());
-        verify_hasher.update(b"client finished");
-        verify_hasher.update(&handshake_hash);
-        let expected_verify = verify_hasher.finalize();
-
-        if msg.payload.as_slice() != expected_verify.as_slice() {
-            return Err("Finished verification failed".to_string());
-        }
+        let finished_key = self
+            .client_finished_key
+            .as_ref()
+            .ok_or("Keys not derived yet".to_string())?;
+        let mut mac =
+            HmacSha256::new_from_slice(finished_key).expect("HMAC accepts any key length");
+        mac.update(&handshake_hash);
+        mac.verify_slice(&msg.payload)
+            .map_err(|_| "Finished verification failed".to_string())?;
 
         Ok(())
     }
@@ -472,14 +538,15 @@ mod tests {
     #[test]
     fn test_key_derivation() {
         let secret = b"shared secret";
-        let context = b"context data";
-        
-        let (client_key, server_key) = derive_keys(secret, context);
-        
-        assert_ne!(client_key, server_key);
-        
-        let (client_key2, server_key2) = derive_keys(secret, context);
-        assert_eq!(client_key, client_key2);
-        assert_eq!(server_key, server_key2);
+
+        let (client_keys, server_keys) = derive_traffic_keys(secret, &[]);
+
+        assert_ne!(client_keys.aead_key, server_keys.aead_key);
+        assert_ne!(client_keys.iv, server_keys.iv);
+        assert_ne!(client_keys.finished_key, server_keys.finished_key);
+
+        let (client_keys2, server_keys2) = derive_traffic_keys(secret, &[]);
+        assert_eq!(client_keys.aead_key, client_keys2.aead_key);
+        assert_eq!(server_keys.aead_key, server_keys2.aead_key);
     }
 }
\ No newline at end of file
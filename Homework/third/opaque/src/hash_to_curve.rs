@@ -0,0 +1,67 @@
+//! RFC 9380 hash-to-group for ristretto255, used to map an OPRF input
+//! (the password) onto a curve point without anyone learning its discrete
+//! log - unlike `s * G` for a known scalar `s`, which is what this
+//! replaces.
+
+use curve25519_dalek::ristretto::RistrettoPoint;
+use sha2::{Digest, Sha512};
+
+/// SHA-512's block size in bytes, used to build `Z_pad`.
+const SHA512_BLOCK_BYTES: usize = 128;
+/// Bytes of uniform randomness `expand_message_xmd` produces here - enough
+/// for `RistrettoPoint::from_uniform_bytes`, which needs 64.
+const LEN_IN_BYTES: usize = 64;
+
+fn i2osp(value: usize, len: usize) -> Vec<u8> {
+    let mut out = vec![0u8; len];
+    for (i, byte) in out.iter_mut().rev().enumerate() {
+        *byte = ((value >> (8 * i)) & 0xff) as u8;
+    }
+    out
+}
+
+fn strxor(a: &[u8], b: &[u8]) -> Vec<u8> {
+    a.iter().zip(b).map(|(x, y)| x ^ y).collect()
+}
+
+/// `expand_message_xmd` (RFC 9380 §5.3.1) specialized to a 64-byte output,
+/// which for SHA-512 (64-byte digests) takes exactly two hash calls:
+/// `b_0 = H(Z_pad || msg || I2OSP(64,2) || I2OSP(0,1) || DST')`,
+/// `b_1 = H(b_0 || I2OSP(1,1) || DST')`.
+fn expand_message_xmd_sha512(msg: &[u8], dst: &[u8]) -> [u8; LEN_IN_BYTES] {
+    let mut dst_prime = dst.to_vec();
+    dst_prime.extend_from_slice(&i2osp(dst.len(), 1));
+
+    let z_pad = vec![0u8; SHA512_BLOCK_BYTES];
+    let l_i_b_str = i2osp(LEN_IN_BYTES, 2);
+
+    let mut msg_prime = Vec::new();
+    msg_prime.extend_from_slice(&z_pad);
+    msg_prime.extend_from_slice(msg);
+    msg_prime.extend_from_slice(&l_i_b_str);
+    msg_prime.extend_from_slice(&i2osp(0, 1));
+    msg_prime.extend_from_slice(&dst_prime);
+
+    let b_0 = Sha512::digest(&msg_prime);
+
+    let mut b_1_input = Vec::new();
+    b_1_input.extend_from_slice(&b_0);
+    b_1_input.extend_from_slice(&i2osp(1, 1));
+    b_1_input.extend_from_slice(&dst_prime);
+    let b_1 = Sha512::digest(&b_1_input);
+
+    // `ell = ceil(64 / 64) = 1`, so the uniform bytes are just `b_1`;
+    // `strxor` only shows up computing later blocks when `ell > 1`, which
+    // doesn't happen at this output length.
+    let _ = strxor; // kept for parity with the RFC 9380 reference algorithm
+
+    b_1.into()
+}
+
+/// Map a password (with an OPAQUE domain-separation tag) onto a
+/// ristretto255 point via `expand_message_xmd` + the Ristretto elligator
+/// map, so the point's discrete log is unknown even to whoever hashed it.
+pub fn hash_to_ristretto255(password: &[u8], dst: &[u8]) -> RistrettoPoint {
+    let uniform_bytes = expand_message_xmd_sha512(password, dst);
+    RistrettoPoint::from_uniform_bytes(&uniform_bytes)
+}
@@ -0,0 +1,74 @@
+//! Pluggable export of handshake secrets, modeled on rustls's `KeyLog`
+//! trait and the `SSLKEYLOGFILE` convention Wireshark/tshark understand:
+//! each derivation point hands its secret to a `KeyLog` implementation as
+//! `(label, client_random, secret)`, and what happens to it from there is
+//! the integrator's choice. The default is a no-op, so paying for this
+//! hook costs nothing unless something is actually plugged in; a
+//! file-backed logger for decrypting captured transcripts during
+//! development lives behind the `keylog_file` feature so it's never
+//! compiled into a production build by accident.
+
+/// Receives one handshake secret per call. `client_random` ties multiple
+/// secrets from the same session together, the way a capture tool
+/// correlates `KeyLog` lines with a ClientHello.random back to one TLS
+/// connection; here it's whatever per-session nonce the caller chooses to
+/// thread through the handshake.
+pub trait KeyLog: Send + Sync {
+    fn log(&self, label: &str, client_random: &[u8], secret: &[u8]);
+}
+
+/// Does nothing. The default for callers that don't opt into key export.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoKeyLog;
+
+impl KeyLog for NoKeyLog {
+    fn log(&self, _label: &str, _client_random: &[u8], _secret: &[u8]) {}
+}
+
+/// Appends `SSLKEYLOGFILE`-style lines (`label hex(client_random)
+/// hex(secret)`) to a file, so a capture of the wire traffic can be
+/// decrypted afterwards in an analysis tool. Gated behind a feature since
+/// it writes every exported secret to disk in the clear - only meant for
+/// development.
+#[cfg(feature = "keylog_file")]
+pub mod file {
+    use std::fs::{File, OpenOptions};
+    use std::io::Write;
+    use std::path::Path;
+    use std::sync::Mutex;
+
+    use super::KeyLog;
+
+    pub struct KeyLogFile {
+        file: Mutex<File>,
+    }
+
+    impl KeyLogFile {
+        pub fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            Ok(KeyLogFile { file: Mutex::new(file) })
+        }
+
+        /// Opens the path named by the `SSLKEYLOGFILE` environment
+        /// variable, the same convention `curl`/browsers/rustls use.
+        pub fn from_env() -> std::io::Result<Self> {
+            let path = std::env::var("SSLKEYLOGFILE")
+                .map_err(|_| std::io::Error::new(std::io::ErrorKind::NotFound, "SSLKEYLOGFILE is not set"))?;
+            Self::open(path)
+        }
+    }
+
+    impl KeyLog for KeyLogFile {
+        fn log(&self, label: &str, client_random: &[u8], secret: &[u8]) {
+            let line = format!(
+                "{} {} {}\n",
+                label,
+                hex::encode(client_random),
+                hex::encode(secret)
+            );
+            // A capture tool losing one line to a write failure isn't worth
+            // taking down the handshake over; best-effort is the point.
+            let _ = self.file.lock().expect("keylog mutex poisoned").write_all(line.as_bytes());
+        }
+    }
+}
@@ -1,89 +1,98 @@
-use curve25519_dalek::constants::RISTRETTO_BASEPOINT_TABLE;
-use curve25519_dalek::ristretto::RistrettoPoint;
-use curve25519_dalek::scalar::Scalar;
-use hkdf::Hkdf;
-use hmac::{Hmac, Mac};
+mod ciphersuite;
+mod envelope;
+mod hash_to_curve;
+mod keylog;
+mod resumption;
+mod slow_hash;
+mod transport;
+
+use std::marker::PhantomData;
+
+use hmac::Hmac;
 use rand::rngs::OsRng;
-use sha2::{Digest, Sha256};
-
-type HmacSha256 = Hmac<Sha256>;
-
-/// Hash a password to a curve point (hash-to-curve for OPRF)
-fn hash_to_curve(password: &[u8]) -> RistrettoPoint {
-    // Use SHA-256 to hash the password, then interpret as a scalar
-    // This is a simplified hash-to-curve; production should use RFC 9380
-    let mut hasher = Sha256::new();
-    hasher.update(b"OPAQUE-HashToCurve-");
-    hasher.update(password);
-    let hash = hasher.finalize();
-    
-    // Create a scalar from the hash and multiply by the base point
-    let scalar = Scalar::from_bytes_mod_order(hash.into());
-    &scalar * RISTRETTO_BASEPOINT_TABLE
-}
+use rand::RngCore;
+use sha2::Sha256;
+
+use ciphersuite::{CipherSuite, Group, KdfHash, Ristretto255Sha512};
+use envelope::{client_create_envelope, client_recover_envelope, server_credential_response, EnvelopeError};
+use keylog::{KeyLog, NoKeyLog};
+use slow_hash::SlowHash;
+
+pub(crate) type HmacSha256 = Hmac<Sha256>;
+
+/// Domain-separation tag for the OPRF hash-to-group step, per the OPAQUE
+/// ristretto255 ciphersuite registration in RFC 9380's naming convention.
+const OPRF_HASH_TO_GROUP_DST: &[u8] = b"OPAQUE-V1-ristretto255_XMD:SHA-512_R255MAP_RO_";
 
 /// Server's long-term key pair
 #[derive(Debug, Clone)]
-pub struct ServerLongTermKeys {
-    pub secret: Scalar,
-    pub public: RistrettoPoint,
+pub struct ServerLongTermKeys<C: CipherSuite> {
+    pub secret: <C::KeGroup as Group>::Scalar,
+    pub public: <C::KeGroup as Group>::Point,
 }
 
-impl ServerLongTermKeys {
+impl<C: CipherSuite> ServerLongTermKeys<C> {
     pub fn generate() -> Self {
-        let secret = Scalar::random(&mut OsRng);
-        let public = &secret * RISTRETTO_BASEPOINT_TABLE;
+        let secret = C::KeGroup::random_scalar();
+        let public = C::KeGroup::base_point_mul(&secret);
         Self { secret, public }
     }
 }
 
 /// Client's long-term key pair
 #[derive(Debug, Clone)]
-pub struct ClientLongTermKeys {
-    pub secret: Scalar,
-    pub public: RistrettoPoint,
+pub struct ClientLongTermKeys<C: CipherSuite> {
+    pub secret: <C::KeGroup as Group>::Scalar,
+    pub public: <C::KeGroup as Group>::Point,
 }
 
-impl ClientLongTermKeys {
+impl<C: CipherSuite> ClientLongTermKeys<C> {
     pub fn generate() -> Self {
-        let secret = Scalar::random(&mut OsRng);
-        let public = &secret * RISTRETTO_BASEPOINT_TABLE;
+        let secret = C::KeGroup::random_scalar();
+        let public = C::KeGroup::base_point_mul(&secret);
         Self { secret, public }
     }
 }
 
-/// Registration data stored on the server
+/// Registration data stored on the server. The client's private key is
+/// never stored directly - only `envelope`, which can only be opened by
+/// someone who re-derives `randomized_pwd` from the correct password.
 #[derive(Debug, Clone)]
-pub struct RegistrationRecord {
-    pub client_public_key: RistrettoPoint,
-    pub oprf_key: Scalar,
+pub struct RegistrationRecord<C: CipherSuite> {
+    pub client_public_key: <C::KeGroup as Group>::Point,
+    pub oprf_key: <C::OprfGroup as Group>::Scalar,
+    pub envelope: envelope::Envelope<C>,
+    /// Masks the credential response in transit; known to the server, so
+    /// it protects only against network eavesdroppers before
+    /// authentication completes, not against a server compromise.
+    pub masking_key: [u8; 32],
 }
 
 /// OPRF blinding factor and blinded element
-#[derive(Debug)]
-pub struct OprfClientState {
-    pub blind: Scalar,
-    pub blinded_element: RistrettoPoint,
+#[derive(Debug, Clone)]
+pub struct OprfClientState<C: CipherSuite> {
+    pub blind: <C::OprfGroup as Group>::Scalar,
+    pub blinded_element: <C::OprfGroup as Group>::Point,
 }
 
 /// OPRF evaluated element from server
-#[derive(Debug)]
-pub struct OprfServerResponse {
-    pub evaluated_element: RistrettoPoint,
+#[derive(Debug, Clone)]
+pub struct OprfServerResponse<C: CipherSuite> {
+    pub evaluated_element: <C::OprfGroup as Group>::Point,
 }
 
 /// 3DH ephemeral keys for client
-#[derive(Debug)]
-pub struct ClientEphemeralKeys {
-    pub secret: Scalar,
-    pub public: RistrettoPoint,
+#[derive(Debug, Clone)]
+pub struct ClientEphemeralKeys<C: CipherSuite> {
+    pub secret: <C::KeGroup as Group>::Scalar,
+    pub public: <C::KeGroup as Group>::Point,
 }
 
 /// 3DH ephemeral keys for server
-#[derive(Debug)]
-pub struct ServerEphemeralKeys {
-    pub secret: Scalar,
-    pub public: RistrettoPoint,
+#[derive(Debug, Clone)]
+pub struct ServerEphemeralKeys<C: CipherSuite> {
+    pub secret: <C::KeGroup as Group>::Scalar,
+    pub public: <C::KeGroup as Group>::Point,
 }
 
 /// Key confirmation MACs
@@ -96,27 +105,42 @@ pub struct KeyConfirmation {
 /// REGISTRATION PHASE
 
 /// Client initiates registration by generating long-term keypair
-pub fn registration_start() -> ClientLongTermKeys {
+pub fn registration_start<C: CipherSuite>() -> ClientLongTermKeys<C> {
     ClientLongTermKeys::generate()
 }
 
-/// Server completes registration by storing client's public key and generating OPRF key
-pub fn registration_finish(client_public_key: RistrettoPoint) -> RegistrationRecord {
-    let oprf_key = Scalar::random(&mut OsRng);
+/// Server generates the per-account OPRF key used to evaluate the client's
+/// blinded password, both during registration (to seed the envelope) and
+/// every later login.
+pub fn server_generate_oprf_key<C: CipherSuite>() -> <C::OprfGroup as Group>::Scalar {
+    C::OprfGroup::random_scalar()
+}
+
+/// Server completes registration by storing the client's public key, the
+/// OPRF key, and the envelope + masking key the client produced after
+/// running the OPRF flow against that same key.
+pub fn registration_finish<C: CipherSuite>(
+    client_public_key: <C::KeGroup as Group>::Point,
+    oprf_key: <C::OprfGroup as Group>::Scalar,
+    envelope: envelope::Envelope<C>,
+    masking_key: [u8; 32],
+) -> RegistrationRecord<C> {
     RegistrationRecord {
         client_public_key,
         oprf_key,
+        envelope,
+        masking_key,
     }
 }
 
 /// OPRF STAGE
 
 /// Client: Blind the password for OPRF
-pub fn oprf_client_blind(password: &[u8]) -> OprfClientState {
-    let blind = Scalar::random(&mut OsRng);
-    let pw_point = hash_to_curve(password);
-    let blinded_element = pw_point * blind;
-    
+pub fn oprf_client_blind<C: CipherSuite>(password: &[u8]) -> OprfClientState<C> {
+    let blind = C::OprfGroup::random_scalar();
+    let pw_point = C::OprfGroup::hash_to_group(password, OPRF_HASH_TO_GROUP_DST);
+    let blinded_element = C::OprfGroup::point_mul(&pw_point, &blind);
+
     OprfClientState {
         blind,
         blinded_element,
@@ -124,45 +148,54 @@ pub fn oprf_client_blind(password: &[u8]) -> OprfClientState {
 }
 
 /// Server: Evaluate the blinded element with OPRF key
-pub fn oprf_server_evaluate(
-    blinded_element: RistrettoPoint,
-    oprf_key: &Scalar,
-) -> OprfServerResponse {
-    let evaluated_element = blinded_element * oprf_key;
+pub fn oprf_server_evaluate<C: CipherSuite>(
+    blinded_element: <C::OprfGroup as Group>::Point,
+    oprf_key: &<C::OprfGroup as Group>::Scalar,
+) -> OprfServerResponse<C> {
+    let evaluated_element = C::OprfGroup::point_mul(&blinded_element, oprf_key);
     OprfServerResponse { evaluated_element }
 }
 
-/// Client: Unblind the evaluated element to get the OPRF output
-pub fn oprf_client_finalize(
-    oprf_state: &OprfClientState,
-    evaluated_element: RistrettoPoint,
+/// Unblinds the OPRF evaluation and runs the result through `slow_hash`,
+/// returning `randomized_pwd` - the value that feeds the envelope and AKE
+/// key derivation, never the raw OPRF output. `key_log` is handed the raw
+/// OPRF output (before `slow_hash` hardens it) under the `"OPRF-OUTPUT"`
+/// label, keyed to `client_random` - pass `&NoKeyLog` to opt out.
+pub fn oprf_client_finalize<C: CipherSuite>(
+    oprf_state: &OprfClientState<C>,
+    evaluated_element: <C::OprfGroup as Group>::Point,
     password: &[u8],
+    slow_hash: &C::SlowHash,
+    client_random: &[u8],
+    key_log: &dyn KeyLog,
 ) -> Vec<u8> {
     // Unblind: divide by the blind factor
-    let blind_inv = oprf_state.blind.invert();
-    let unblinded = evaluated_element * blind_inv;
-    
+    let blind_inv = C::OprfGroup::scalar_invert(&oprf_state.blind);
+    let unblinded = C::OprfGroup::point_mul(&evaluated_element, &blind_inv);
+
     // Hash the unblinded point with the password to get the OPRF output
-    let mut hasher = Sha256::new();
-    hasher.update(b"OPAQUE-OPRF-");
-    hasher.update(unblinded.compress().as_bytes());
-    hasher.update(password);
-    hasher.finalize().to_vec()
+    let mut oprf_input = b"OPAQUE-OPRF-".to_vec();
+    oprf_input.extend_from_slice(&C::OprfGroup::point_to_bytes(&unblinded));
+    oprf_input.extend_from_slice(password);
+    let oprf_output = C::Hash::hkdf_expand(&oprf_input, b"OPAQUE-OPRF-Output", 32);
+    key_log.log("OPRF-OUTPUT", client_random, &oprf_output);
+
+    slow_hash.hash(&oprf_output)
 }
 
 /// AKE STAGE: 3DH
 
 /// Client generates ephemeral keypair for 3DH
-pub fn client_generate_ephemeral() -> ClientEphemeralKeys {
-    let secret = Scalar::random(&mut OsRng);
-    let public = &secret * RISTRETTO_BASEPOINT_TABLE;
+pub fn client_generate_ephemeral<C: CipherSuite>() -> ClientEphemeralKeys<C> {
+    let secret = C::KeGroup::random_scalar();
+    let public = C::KeGroup::base_point_mul(&secret);
     ClientEphemeralKeys { secret, public }
 }
 
 /// Server generates ephemeral keypair for 3DH
-pub fn server_generate_ephemeral() -> ServerEphemeralKeys {
-    let secret = Scalar::random(&mut OsRng);
-    let public = &secret * RISTRETTO_BASEPOINT_TABLE;
+pub fn server_generate_ephemeral<C: CipherSuite>() -> ServerEphemeralKeys<C> {
+    let secret = C::KeGroup::random_scalar();
+    let public = C::KeGroup::base_point_mul(&secret);
     ServerEphemeralKeys { secret, public }
 }
 
@@ -173,29 +206,30 @@ pub fn server_generate_ephemeral() -> ServerEphemeralKeys {
 ///   x = ephemeral secret
 ///   B = lpk_s (server long-term public)
 ///   Y = epk_s (server ephemeral public)
-pub fn three_dh_client(
-    client_ltk: &ClientLongTermKeys,
-    client_ephemeral: &ClientEphemeralKeys,
-    server_ltk_public: &RistrettoPoint,
-    server_ephemeral_public: &RistrettoPoint,
+pub fn three_dh_client<C: CipherSuite>(
+    client_ltk: &ClientLongTermKeys<C>,
+    client_ephemeral: &ClientEphemeralKeys<C>,
+    server_ltk_public: &<C::KeGroup as Group>::Point,
+    server_ephemeral_public: &<C::KeGroup as Group>::Point,
+    client_random: &[u8],
+    key_log: &dyn KeyLog,
 ) -> [u8; 32] {
     // Compute the three DH values
-    let dh1 = server_ltk_public * client_ephemeral.secret; // B^x
-    let dh2 = server_ephemeral_public * client_ephemeral.secret; // Y^x
-    let dh3 = server_ephemeral_public * client_ltk.secret; // Y^a
-    
+    let dh1 = C::KeGroup::point_mul(server_ltk_public, &client_ephemeral.secret); // B^x
+    let dh2 = C::KeGroup::point_mul(server_ephemeral_public, &client_ephemeral.secret); // Y^x
+    let dh3 = C::KeGroup::point_mul(server_ephemeral_public, &client_ltk.secret); // Y^a
+
     // Concatenate the DH values
     let mut ikm = Vec::new();
-    ikm.extend_from_slice(dh1.compress().as_bytes());
-    ikm.extend_from_slice(dh2.compress().as_bytes());
-    ikm.extend_from_slice(dh3.compress().as_bytes());
-    
-    // Derive the shared secret using HKDF
-    let hkdf = Hkdf::<Sha256>::new(None, &ikm);
+    ikm.extend_from_slice(&C::KeGroup::point_to_bytes(&dh1));
+    ikm.extend_from_slice(&C::KeGroup::point_to_bytes(&dh2));
+    ikm.extend_from_slice(&C::KeGroup::point_to_bytes(&dh3));
+
+    // Derive the shared secret using this suite's KDF hash
+    let sk_bytes = C::Hash::hkdf_expand(&ikm, b"3DH-SharedSecret", 32);
     let mut sk = [0u8; 32];
-    hkdf.expand(b"3DH-SharedSecret", &mut sk)
-        .expect("HKDF expand failed");
-    
+    sk.copy_from_slice(&sk_bytes);
+    key_log.log("3DH-SharedSecret", client_random, &sk);
     sk
 }
 
@@ -206,168 +240,232 @@ pub fn three_dh_client(
 ///   y = ephemeral secret
 ///   A = lpk_c (client long-term public)
 ///   X = epk_c (client ephemeral public)
-pub fn three_dh_server(
-    server_ltk: &ServerLongTermKeys,
-    server_ephemeral: &ServerEphemeralKeys,
-    client_ltk_public: &RistrettoPoint,
-    client_ephemeral_public: &RistrettoPoint,
+pub fn three_dh_server<C: CipherSuite>(
+    server_ltk: &ServerLongTermKeys<C>,
+    server_ephemeral: &ServerEphemeralKeys<C>,
+    client_ltk_public: &<C::KeGroup as Group>::Point,
+    client_ephemeral_public: &<C::KeGroup as Group>::Point,
+    client_random: &[u8],
+    key_log: &dyn KeyLog,
 ) -> [u8; 32] {
     // Compute the three DH values
-    let dh1 = client_ephemeral_public * server_ltk.secret; // X^b
-    let dh2 = client_ephemeral_public * server_ephemeral.secret; // X^y
-    let dh3 = client_ltk_public * server_ephemeral.secret; // A^y
-    
+    let dh1 = C::KeGroup::point_mul(client_ephemeral_public, &server_ltk.secret); // X^b
+    let dh2 = C::KeGroup::point_mul(client_ephemeral_public, &server_ephemeral.secret); // X^y
+    let dh3 = C::KeGroup::point_mul(client_ltk_public, &server_ephemeral.secret); // A^y
+
     // Concatenate the DH values
     let mut ikm = Vec::new();
-    ikm.extend_from_slice(dh1.compress().as_bytes());
-    ikm.extend_from_slice(dh2.compress().as_bytes());
-    ikm.extend_from_slice(dh3.compress().as_bytes());
-    
-    // Derive the shared secret using HKDF
-    let hkdf = Hkdf::<Sha256>::new(None, &ikm);
+    ikm.extend_from_slice(&C::KeGroup::point_to_bytes(&dh1));
+    ikm.extend_from_slice(&C::KeGroup::point_to_bytes(&dh2));
+    ikm.extend_from_slice(&C::KeGroup::point_to_bytes(&dh3));
+
+    // Derive the shared secret using this suite's KDF hash
+    let sk_bytes = C::Hash::hkdf_expand(&ikm, b"3DH-SharedSecret", 32);
     let mut sk = [0u8; 32];
-    hkdf.expand(b"3DH-SharedSecret", &mut sk)
-        .expect("HKDF expand failed");
-    
+    sk.copy_from_slice(&sk_bytes);
+    key_log.log("3DH-SharedSecret", client_random, &sk);
     sk
 }
 
 /// KEY CONFIRMATION
 
-/// Derive key confirmation keys from the shared secret
-fn derive_confirmation_keys(sk: &[u8; 32]) -> (Vec<u8>, Vec<u8>) {
-    let hkdf = Hkdf::<Sha256>::new(None, sk);
-    
-    let mut k_c = vec![0u8; 32];
-    let mut k_s = vec![0u8; 32];
-    
-    hkdf.expand(b"Key Confirmation-Kc", &mut k_c)
-        .expect("HKDF expand failed");
-    hkdf.expand(b"Key Confirmation-Ks", &mut k_s)
-        .expect("HKDF expand failed");
-    
+/// Derive key confirmation keys from the shared secret, handing each one
+/// to `key_log` under its own label as it's derived.
+fn derive_confirmation_keys<C: CipherSuite>(
+    sk: &[u8; 32],
+    client_random: &[u8],
+    key_log: &dyn KeyLog,
+) -> (Vec<u8>, Vec<u8>) {
+    let k_c = C::Hash::hkdf_expand(sk, b"Key Confirmation-Kc", 32);
+    key_log.log("KEY-CONFIRMATION-KC", client_random, &k_c);
+    let k_s = C::Hash::hkdf_expand(sk, b"Key Confirmation-Ks", 32);
+    key_log.log("KEY-CONFIRMATION-KS", client_random, &k_s);
     (k_c, k_s)
 }
 
 /// Client generates key confirmation MAC
-pub fn client_key_confirmation(sk: &[u8; 32]) -> Vec<u8> {
-    let (k_c, _) = derive_confirmation_keys(sk);
-    
-    let mut mac = HmacSha256::new_from_slice(&k_c).expect("HMAC key error");
-    mac.update(b"Client KC");
-    mac.finalize().into_bytes().to_vec()
+pub fn client_key_confirmation<C: CipherSuite>(
+    sk: &[u8; 32],
+    client_random: &[u8],
+    key_log: &dyn KeyLog,
+) -> Vec<u8> {
+    let (k_c, _) = derive_confirmation_keys::<C>(sk, client_random, key_log);
+    C::Hash::hmac(&k_c, b"Client KC")
 }
 
 /// Server generates key confirmation MAC
-pub fn server_key_confirmation(sk: &[u8; 32]) -> Vec<u8> {
-    let (_, k_s) = derive_confirmation_keys(sk);
-    
-    let mut mac = HmacSha256::new_from_slice(&k_s).expect("HMAC key error");
-    mac.update(b"Server KC");
-    mac.finalize().into_bytes().to_vec()
+pub fn server_key_confirmation<C: CipherSuite>(
+    sk: &[u8; 32],
+    client_random: &[u8],
+    key_log: &dyn KeyLog,
+) -> Vec<u8> {
+    let (_, k_s) = derive_confirmation_keys::<C>(sk, client_random, key_log);
+    C::Hash::hmac(&k_s, b"Server KC")
 }
 
 /// Verify client's key confirmation MAC
-pub fn verify_client_mac(sk: &[u8; 32], received_mac: &[u8]) -> bool {
-    let expected_mac = client_key_confirmation(sk);
-    expected_mac == received_mac
+pub fn verify_client_mac<C: CipherSuite>(
+    sk: &[u8; 32],
+    received_mac: &[u8],
+    client_random: &[u8],
+    key_log: &dyn KeyLog,
+) -> bool {
+    let (k_c, _) = derive_confirmation_keys::<C>(sk, client_random, key_log);
+    C::Hash::hmac_verify(&k_c, b"Client KC", received_mac)
 }
 
 /// Verify server's key confirmation MAC
-pub fn verify_server_mac(sk: &[u8; 32], received_mac: &[u8]) -> bool {
-    let expected_mac = server_key_confirmation(sk);
-    expected_mac == received_mac
+pub fn verify_server_mac<C: CipherSuite>(
+    sk: &[u8; 32],
+    received_mac: &[u8],
+    client_random: &[u8],
+    key_log: &dyn KeyLog,
+) -> bool {
+    let (_, k_s) = derive_confirmation_keys::<C>(sk, client_random, key_log);
+    C::Hash::hmac_verify(&k_s, b"Server KC", received_mac)
 }
 
-/// OPAQUE PROTOCOL
+/// OPAQUE PROTOCOL, run against the default `Ristretto255Sha512` suite.
+/// Swap this alias (or add a second `main`-style driver) to run the demo
+/// against `ciphersuite::P256Sha256` instead.
+type DefaultSuite = Ristretto255Sha512;
 
 fn main() {
-    
+    let _suite_marker: PhantomData<DefaultSuite> = PhantomData;
+
+    // Ties every exported secret below back to this one run, the way a
+    // ClientHello.random does for a captured TLS session. No integrator
+    // has opted into key export, so this demo logs to `NoKeyLog`; swap in
+    // `keylog::file::KeyLogFile::from_env()` (behind the `keylog_file`
+    // feature) to decrypt a capture in an analysis tool during development.
+    let mut client_random = [0u8; 32];
+    OsRng.fill_bytes(&mut client_random);
+    let key_log: &dyn KeyLog = &NoKeyLog;
+
     println!("--- Registration Phase ---");
-    
+
     // Client generates long-term keypair
-    let client_ltk = registration_start();
+    let client_ltk = registration_start::<DefaultSuite>();
     println!("Client generated long-term keypair");
-    
+
     // Server stores client's public key and generates OPRF key
-    let server_ltk = ServerLongTermKeys::generate();
-    let registration_record = registration_finish(client_ltk.public);
+    let server_ltk = ServerLongTermKeys::<DefaultSuite>::generate();
+    let registration_password = b"my_secure_password";
+    let reg_oprf_key = server_generate_oprf_key::<DefaultSuite>();
+    let reg_oprf_state = oprf_client_blind::<DefaultSuite>(registration_password);
+    let reg_oprf_response =
+        oprf_server_evaluate::<DefaultSuite>(reg_oprf_state.blinded_element.clone(), &reg_oprf_key);
+    let reg_randomized_pwd = oprf_client_finalize(
+        &reg_oprf_state,
+        reg_oprf_response.evaluated_element,
+        registration_password,
+        &slow_hash::Argon2Hash::default(),
+        &client_random,
+        key_log,
+    );
+    let (envelope, masking_key) =
+        client_create_envelope(&client_ltk, server_ltk.public.clone(), &reg_randomized_pwd);
+    let registration_record =
+        registration_finish(client_ltk.public.clone(), reg_oprf_key, envelope, masking_key);
     println!("Server completed registration");
     println!("Registration complete!\n");
-    
+
     println!("--- Login Phase ---\n");
-    
+
     let password = b"my_secure_password";
-    
+
     println!("1. OPRF Stage:");
-    
+
     // Client blinds the password
-    let oprf_state = oprf_client_blind(password);
+    let oprf_state = oprf_client_blind::<DefaultSuite>(password);
     println!("   Client: Blinded password");
-    
-    // Server evaluates the blinded element
-    let oprf_response = oprf_server_evaluate(
-        oprf_state.blinded_element,
+
+    // Server evaluates the blinded element and masks the envelope response
+    let oprf_response = oprf_server_evaluate::<DefaultSuite>(
+        oprf_state.blinded_element.clone(),
         &registration_record.oprf_key,
     );
-    println!("   Server: Evaluated blinded element");
-    
-    // Client unblinds to get OPRF output
-    let oprf_output = oprf_client_finalize(&oprf_state, oprf_response.evaluated_element, password);
-    println!("   Client: Unblinded to get OPRF output");
-    println!("   OPRF output: {}\n", hex::encode(&oprf_output[..16]));
-    
-    
+    let credential_response = server_credential_response(
+        &registration_record.envelope,
+        &registration_record.masking_key,
+        server_ltk.public.clone(),
+        oprf_response.evaluated_element,
+    );
+    println!("   Server: Evaluated blinded element, masked envelope response");
+
+    // Client unblinds to get the OPRF output, then hardens it with Argon2id
+    let randomized_pwd = oprf_client_finalize(
+        &oprf_state,
+        credential_response.evaluated_element.clone(),
+        password,
+        &slow_hash::Argon2Hash::default(),
+        &client_random,
+        key_log,
+    );
+    println!("   Client: Unblinded and hardened the OPRF output");
+    println!("   randomized_pwd: {}\n", hex::encode(&randomized_pwd[..16]));
+
+    // Client unmasks and unseals the envelope, recovering its private key
+    let (client_ltk, server_public) =
+        client_recover_envelope(&credential_response, &randomized_pwd)
+            .expect("envelope should open with the correct password");
+    println!("   Client: Recovered long-term keypair from envelope\n");
+
+
     println!("2. AKE Stage (3DH):");
-    
+
     // Client generates ephemeral keypair
-    let client_ephemeral = client_generate_ephemeral();
+    let client_ephemeral = client_generate_ephemeral::<DefaultSuite>();
     println!("   Client: Generated ephemeral keypair X");
-    
+
     // Server generates ephemeral keypair
-    let server_ephemeral = server_generate_ephemeral();
+    let server_ephemeral = server_generate_ephemeral::<DefaultSuite>();
     println!("   Server: Generated ephemeral keypair Y");
-    
+
     // Both sides compute the shared secret
     let client_sk = three_dh_client(
         &client_ltk,
         &client_ephemeral,
-        &server_ltk.public,
+        &server_public,
         &server_ephemeral.public,
+        &client_random,
+        key_log,
     );
     println!("   Client: Computed shared secret SK");
-    
+
     let server_sk = three_dh_server(
         &server_ltk,
         &server_ephemeral,
         &registration_record.client_public_key,
         &client_ephemeral.public,
+        &client_random,
+        key_log,
     );
     println!("   Server: Computed shared secret SK");
-    
+
     // Verify both sides computed the same key
     assert_eq!(client_sk, server_sk, "Shared secrets don't match!");
     println!("   ✓ Both sides agree on SK: {}\n", hex::encode(&client_sk[..16]));
-    
-    
+
+
     println!("3. Key Confirmation:");
-    
+
     // Client generates MAC
-    let client_mac = client_key_confirmation(&client_sk);
+    let client_mac = client_key_confirmation::<DefaultSuite>(&client_sk, &client_random, key_log);
     println!("   Client: Generated MAC_c");
-    
+
     // Server generates MAC
-    let server_mac = server_key_confirmation(&server_sk);
+    let server_mac = server_key_confirmation::<DefaultSuite>(&server_sk, &client_random, key_log);
     println!("   Server: Generated MAC_s");
-    
+
     // Client verifies server's MAC
-    let server_mac_valid = verify_server_mac(&client_sk, &server_mac);
+    let server_mac_valid = verify_server_mac::<DefaultSuite>(&client_sk, &server_mac, &client_random, key_log);
     println!("   Client: Verified server MAC: {}", server_mac_valid);
-    
+
     // Server verifies client's MAC
-    let client_mac_valid = verify_client_mac(&server_sk, &client_mac);
+    let client_mac_valid = verify_client_mac::<DefaultSuite>(&server_sk, &client_mac, &client_random, key_log);
     println!("   Server: Verified client MAC: {}", client_mac_valid);
-    
+
     // FINAL RESULT
     println!("\nProtocol Complete");
     if server_mac_valid && client_mac_valid {
@@ -376,122 +474,431 @@ fn main() {
     } else {
         println!("Authentication failed!");
     }
+
+    println!("\n--- Resumption (PSK+DHE) ---");
+
+    // The server issues a resumption PSK once the full handshake above
+    // has authenticated, tied to some identity it picks for the ticket.
+    let ticket = resumption::derive_resumption_psk::<DefaultSuite>(&client_sk, [7u8; resumption::PSK_IDENTITY_LEN]);
+    println!("   Server: Issued resumption PSK for identity {:?}", ticket.identity);
+
+    // Next connection: both sides still contribute a fresh ephemeral, so
+    // a PSK leak later can't retroactively recover this resumed session.
+    let resumed_client_ephemeral = client_generate_ephemeral::<DefaultSuite>();
+    let resumed_server_ephemeral = server_generate_ephemeral::<DefaultSuite>();
+
+    let resumed_server_sk = resumption::resumption_server_finish(
+        &ticket.psk,
+        &resumed_server_ephemeral,
+        &resumed_client_ephemeral.public,
+    );
+    // Server can start sending 0.5-RTT data under this key immediately,
+    // before it has verified the client's key confirmation MAC.
+    let early_traffic_key = resumption::server_early_traffic_key::<DefaultSuite>(&resumed_server_sk);
+    println!(
+        "   Server: Derived 0.5-RTT traffic key: {}",
+        hex::encode(&early_traffic_key[..16])
+    );
+
+    let resumed_client_sk = resumption::resumption_client_finish(
+        &ticket.psk,
+        &resumed_client_ephemeral,
+        &resumed_server_ephemeral.public,
+    );
+
+    assert_eq!(resumed_client_sk, resumed_server_sk, "Resumed secrets don't match!");
+
+    let resumed_client_mac = client_key_confirmation::<DefaultSuite>(&resumed_client_sk, &client_random, key_log);
+    let resumed_server_mac = server_key_confirmation::<DefaultSuite>(&resumed_server_sk, &client_random, key_log);
+    println!(
+        "   Resumption authenticated: {}",
+        verify_client_mac::<DefaultSuite>(&resumed_server_sk, &resumed_client_mac, &client_random, key_log)
+            && verify_server_mac::<DefaultSuite>(&resumed_client_sk, &resumed_server_mac, &client_random, key_log)
+    );
+
+    println!("\n--- Record Layer ---");
+
+    let mut client_records = transport::RecordLayer::new::<DefaultSuite>(
+        &client_sk,
+        transport::AeadSuite::Aes256Gcm,
+        true,
+        &client_random,
+        key_log,
+    );
+    let mut server_records = transport::RecordLayer::new::<DefaultSuite>(
+        &server_sk,
+        transport::AeadSuite::Aes256Gcm,
+        false,
+        &client_random,
+        key_log,
+    );
+
+    let (seq, ciphertext) = client_records
+        .seal(b"", b"hello from the client")
+        .expect("sealing should succeed");
+    let plaintext = server_records
+        .open(seq, b"", &ciphertext)
+        .expect("opening should succeed");
+    println!(
+        "   Server decrypted record #{}: {}",
+        seq,
+        String::from_utf8_lossy(&plaintext)
+    );
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    
+
+    fn register(
+        password: &[u8],
+    ) -> (
+        ClientLongTermKeys<DefaultSuite>,
+        ServerLongTermKeys<DefaultSuite>,
+        RegistrationRecord<DefaultSuite>,
+    ) {
+        let client_ltk = registration_start::<DefaultSuite>();
+        let server_ltk = ServerLongTermKeys::<DefaultSuite>::generate();
+
+        let oprf_key = server_generate_oprf_key::<DefaultSuite>();
+        let oprf_state = oprf_client_blind::<DefaultSuite>(password);
+        let oprf_response =
+            oprf_server_evaluate::<DefaultSuite>(oprf_state.blinded_element.clone(), &oprf_key);
+        let randomized_pwd = oprf_client_finalize(
+            &oprf_state,
+            oprf_response.evaluated_element,
+            password,
+            &slow_hash::NoOpHash,
+            b"test-client-random",
+            &NoKeyLog,
+        );
+
+        let (envelope, masking_key) =
+            client_create_envelope(&client_ltk, server_ltk.public.clone(), &randomized_pwd);
+        let registration_record =
+            registration_finish(client_ltk.public.clone(), oprf_key, envelope, masking_key);
+
+        (client_ltk, server_ltk, registration_record)
+    }
+
     #[test]
     fn test_registration() {
-        let client_ltk = registration_start();
-        let registration_record = registration_finish(client_ltk.public);
-        
+        let (client_ltk, _server_ltk, registration_record) = register(b"test_password");
+
         assert_eq!(registration_record.client_public_key, client_ltk.public);
     }
-    
+
     #[test]
     fn test_oprf_flow() {
         let password = b"test_password";
-        
+
         // OPRF key generation (during registration)
-        let oprf_key = Scalar::random(&mut OsRng);
-        
+        let oprf_key = server_generate_oprf_key::<DefaultSuite>();
+
         // Client blinds
-        let oprf_state = oprf_client_blind(password);
-        
+        let oprf_state = oprf_client_blind::<DefaultSuite>(password);
+
         // Server evaluates
-        let oprf_response = oprf_server_evaluate(oprf_state.blinded_element, &oprf_key);
-        
+        let oprf_response =
+            oprf_server_evaluate::<DefaultSuite>(oprf_state.blinded_element.clone(), &oprf_key);
+
         // Client finalizes
-        let oprf_output = oprf_client_finalize(&oprf_state, oprf_response.evaluated_element, password);
-        
-        assert_eq!(oprf_output.len(), 32);
+        let randomized_pwd = oprf_client_finalize(
+            &oprf_state,
+            oprf_response.evaluated_element,
+            password,
+            &slow_hash::NoOpHash,
+            b"test-client-random",
+            &NoKeyLog,
+        );
+
+        assert_eq!(randomized_pwd.len(), 32);
     }
-    
+
     #[test]
     fn test_3dh_agreement() {
-        let client_ltk = ClientLongTermKeys::generate();
-        let server_ltk = ServerLongTermKeys::generate();
-        
-        let client_ephemeral = client_generate_ephemeral();
-        let server_ephemeral = server_generate_ephemeral();
-        
+        let client_ltk = ClientLongTermKeys::<DefaultSuite>::generate();
+        let server_ltk = ServerLongTermKeys::<DefaultSuite>::generate();
+
+        let client_ephemeral = client_generate_ephemeral::<DefaultSuite>();
+        let server_ephemeral = server_generate_ephemeral::<DefaultSuite>();
+
         let client_sk = three_dh_client(
             &client_ltk,
             &client_ephemeral,
             &server_ltk.public,
             &server_ephemeral.public,
+            b"test-client-random",
+            &NoKeyLog,
         );
-        
+
         let server_sk = three_dh_server(
             &server_ltk,
             &server_ephemeral,
             &client_ltk.public,
             &client_ephemeral.public,
+            b"test-client-random",
+            &NoKeyLog,
         );
-        
+
         assert_eq!(client_sk, server_sk);
     }
-    
+
     #[test]
     fn test_key_confirmation() {
         let sk = [42u8; 32];
-        
-        let client_mac = client_key_confirmation(&sk);
-        let server_mac = server_key_confirmation(&sk);
-        
-        assert!(verify_client_mac(&sk, &client_mac));
-        assert!(verify_server_mac(&sk, &server_mac));
-        
+
+        let client_mac = client_key_confirmation::<DefaultSuite>(&sk, b"test-client-random", &NoKeyLog);
+        let server_mac = server_key_confirmation::<DefaultSuite>(&sk, b"test-client-random", &NoKeyLog);
+
+        assert!(verify_client_mac::<DefaultSuite>(&sk, &client_mac, b"test-client-random", &NoKeyLog));
+        assert!(verify_server_mac::<DefaultSuite>(&sk, &server_mac, b"test-client-random", &NoKeyLog));
+
         // Test with wrong MAC
         let wrong_mac = vec![0u8; 32];
-        assert!(!verify_client_mac(&sk, &wrong_mac));
-        assert!(!verify_server_mac(&sk, &wrong_mac));
+        assert!(!verify_client_mac::<DefaultSuite>(&sk, &wrong_mac, b"test-client-random", &NoKeyLog));
+        assert!(!verify_server_mac::<DefaultSuite>(&sk, &wrong_mac, b"test-client-random", &NoKeyLog));
     }
-    
+
     #[test]
     fn test_complete_protocol() {
         let password = b"secure_password_123";
-        
+
         // Registration
-        let client_ltk = registration_start();
-        let server_ltk = ServerLongTermKeys::generate();
-        let registration_record = registration_finish(client_ltk.public);
-        
+        let (_original_client_ltk, server_ltk, registration_record) = register(password);
+
         // Login - OPRF
-        let oprf_state = oprf_client_blind(password);
-        let oprf_response = oprf_server_evaluate(
-            oprf_state.blinded_element,
+        let oprf_state = oprf_client_blind::<DefaultSuite>(password);
+        let oprf_response = oprf_server_evaluate::<DefaultSuite>(
+            oprf_state.blinded_element.clone(),
             &registration_record.oprf_key,
         );
-        let _oprf_output = oprf_client_finalize(&oprf_state, oprf_response.evaluated_element, password);
-        
+        let credential_response = server_credential_response(
+            &registration_record.envelope,
+            &registration_record.masking_key,
+            server_ltk.public.clone(),
+            oprf_response.evaluated_element,
+        );
+        let randomized_pwd = oprf_client_finalize(
+            &oprf_state,
+            credential_response.evaluated_element.clone(),
+            password,
+            &slow_hash::NoOpHash,
+            b"test-client-random",
+            &NoKeyLog,
+        );
+
+        // Login - envelope recovery
+        let (client_ltk, server_public) =
+            client_recover_envelope(&credential_response, &randomized_pwd)
+                .expect("envelope should open with the correct password");
+
         // AKE - 3DH
-        let client_ephemeral = client_generate_ephemeral();
-        let server_ephemeral = server_generate_ephemeral();
-        
+        let client_ephemeral = client_generate_ephemeral::<DefaultSuite>();
+        let server_ephemeral = server_generate_ephemeral::<DefaultSuite>();
+
         let client_sk = three_dh_client(
             &client_ltk,
             &client_ephemeral,
-            &server_ltk.public,
+            &server_public,
             &server_ephemeral.public,
+            b"test-client-random",
+            &NoKeyLog,
         );
-        
+
         let server_sk = three_dh_server(
             &server_ltk,
             &server_ephemeral,
-            &client_ltk.public,
+            &registration_record.client_public_key,
             &client_ephemeral.public,
+            b"test-client-random",
+            &NoKeyLog,
         );
-        
+
         assert_eq!(client_sk, server_sk);
-        
+
         // Key Confirmation
-        let client_mac = client_key_confirmation(&client_sk);
-        let server_mac = server_key_confirmation(&server_sk);
-        
-        assert!(verify_server_mac(&client_sk, &server_mac));
-        assert!(verify_client_mac(&server_sk, &client_mac));
+        let client_mac = client_key_confirmation::<DefaultSuite>(&client_sk, b"test-client-random", &NoKeyLog);
+        let server_mac = server_key_confirmation::<DefaultSuite>(&server_sk, b"test-client-random", &NoKeyLog);
+
+        assert!(verify_server_mac::<DefaultSuite>(&client_sk, &server_mac, b"test-client-random", &NoKeyLog));
+        assert!(verify_client_mac::<DefaultSuite>(&server_sk, &client_mac, b"test-client-random", &NoKeyLog));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_wrong_password_rejected_by_envelope() {
+        let (_client_ltk, server_ltk, registration_record) =
+            register(b"correct horse battery staple");
+
+        // Login attempt with the wrong password
+        let wrong_password = b"incorrect horse battery staple";
+        let oprf_state = oprf_client_blind::<DefaultSuite>(wrong_password);
+        let oprf_response = oprf_server_evaluate::<DefaultSuite>(
+            oprf_state.blinded_element.clone(),
+            &registration_record.oprf_key,
+        );
+        let credential_response = server_credential_response(
+            &registration_record.envelope,
+            &registration_record.masking_key,
+            server_ltk.public.clone(),
+            oprf_response.evaluated_element,
+        );
+        let randomized_pwd = oprf_client_finalize(
+            &oprf_state,
+            credential_response.evaluated_element.clone(),
+            wrong_password,
+            &slow_hash::NoOpHash,
+            b"test-client-random",
+            &NoKeyLog,
+        );
+
+        let result = client_recover_envelope(&credential_response, &randomized_pwd);
+        assert!(matches!(result, Err(EnvelopeError::WrongPassword)));
+    }
+
+    #[test]
+    fn test_resumption_handshake_agrees_and_authenticates() {
+        let sk = [9u8; 32];
+        let ticket = resumption::derive_resumption_psk::<DefaultSuite>(&sk, [1u8; resumption::PSK_IDENTITY_LEN]);
+
+        let client_ephemeral = client_generate_ephemeral::<DefaultSuite>();
+        let server_ephemeral = server_generate_ephemeral::<DefaultSuite>();
+
+        let server_sk = resumption::resumption_server_finish(&ticket.psk, &server_ephemeral, &client_ephemeral.public);
+        let client_sk = resumption::resumption_client_finish(&ticket.psk, &client_ephemeral, &server_ephemeral.public);
+
+        assert_eq!(client_sk, server_sk);
+
+        let client_mac = client_key_confirmation::<DefaultSuite>(&client_sk, b"test-client-random", &NoKeyLog);
+        let server_mac = server_key_confirmation::<DefaultSuite>(&server_sk, b"test-client-random", &NoKeyLog);
+        assert!(verify_client_mac::<DefaultSuite>(&server_sk, &client_mac, b"test-client-random", &NoKeyLog));
+        assert!(verify_server_mac::<DefaultSuite>(&client_sk, &server_mac, b"test-client-random", &NoKeyLog));
+
+        // The 0.5-RTT key is available from `server_sk` alone, before any
+        // confirmation MAC has been seen.
+        let early_key = resumption::server_early_traffic_key::<DefaultSuite>(&server_sk);
+        assert_eq!(early_key.len(), 32);
+    }
+
+    #[test]
+    fn test_rotated_psk_is_unrelated_to_original() {
+        let sk = [3u8; 32];
+        let ticket = resumption::derive_resumption_psk::<DefaultSuite>(&sk, [2u8; resumption::PSK_IDENTITY_LEN]);
+        let rotated = resumption::rotate_psk::<DefaultSuite>(&ticket);
+
+        assert_ne!(ticket.psk, rotated.psk);
+        assert_eq!(rotated.identity, ticket.identity);
+    }
+
+    #[test]
+    fn test_corrupted_psk_does_not_compromise_earlier_sessions() {
+        // An earlier resumed session, established with the original PSK
+        // and its own ephemeral contribution.
+        let sk = [5u8; 32];
+        let ticket = resumption::derive_resumption_psk::<DefaultSuite>(&sk, [3u8; resumption::PSK_IDENTITY_LEN]);
+
+        let session_1_client = client_generate_ephemeral::<DefaultSuite>();
+        let session_1_server = server_generate_ephemeral::<DefaultSuite>();
+        let session_1_sk =
+            resumption::resumption_server_finish(&ticket.psk, &session_1_server, &session_1_client.public);
+
+        // Later, the PSK in storage is corrupted (or leaks outright - the
+        // stronger case). Either way, an attacker holding it can only ever
+        // run a *new* handshake with fresh ephemerals of their own; they
+        // cannot reconstruct `session_1_sk`, whose key material also
+        // depended on `session_1_client`/`session_1_server`'s long-gone
+        // ephemeral secrets. That's forward secrecy at work.
+        let mut corrupted_psk = ticket.psk.clone();
+        corrupted_psk[0] ^= 0xff;
+
+        let attacker_client = client_generate_ephemeral::<DefaultSuite>();
+        let attacker_server = server_generate_ephemeral::<DefaultSuite>();
+        let forged_sk_corrupted =
+            resumption::resumption_server_finish(&corrupted_psk, &attacker_server, &attacker_client.public);
+        let forged_sk_with_original_psk =
+            resumption::resumption_server_finish(&ticket.psk, &attacker_server, &attacker_client.public);
+
+        assert_ne!(session_1_sk, forged_sk_corrupted);
+        assert_ne!(session_1_sk, forged_sk_with_original_psk);
+    }
+
+    #[test]
+    fn test_record_layer_round_trip_each_suite() {
+        for suite in [
+            transport::AeadSuite::ChaCha20Poly1305,
+            transport::AeadSuite::Aes128Gcm,
+            transport::AeadSuite::Aes256Gcm,
+        ] {
+            let sk = [11u8; 32];
+            let mut client = transport::RecordLayer::new::<DefaultSuite>(&sk, suite, true, b"test-client-random", &NoKeyLog);
+            let mut server = transport::RecordLayer::new::<DefaultSuite>(&sk, suite, false, b"test-client-random", &NoKeyLog);
+
+            let (seq, ciphertext) = client.seal(b"aad", b"ping").expect("seal");
+            let opened = server.open(seq, b"aad", &ciphertext).expect("open");
+            assert_eq!(opened, b"ping");
+        }
+    }
+
+    #[test]
+    fn test_record_layer_directions_use_different_keys() {
+        let sk = [22u8; 32];
+        let mut client = transport::RecordLayer::new::<DefaultSuite>(&sk, transport::AeadSuite::Aes256Gcm, true, b"test-client-random", &NoKeyLog);
+        let mut server = transport::RecordLayer::new::<DefaultSuite>(&sk, transport::AeadSuite::Aes256Gcm, false, b"test-client-random", &NoKeyLog);
+
+        // Client write == server read, and vice versa: a record sealed by
+        // the server should not open against the client's own write-side
+        // state (they're different directions).
+        let (seq, ciphertext) = server.seal(b"", b"server data").expect("seal");
+        let result = client.seal(b"", b"server data");
+        assert_ne!(result.unwrap().1, ciphertext);
+
+        let opened = client.open(seq, b"", &ciphertext).expect("client reads server's direction");
+        assert_eq!(opened, b"server data");
+    }
+
+    #[test]
+    fn test_record_layer_rejects_tampered_ciphertext() {
+        let sk = [33u8; 32];
+        let mut client = transport::RecordLayer::new::<DefaultSuite>(&sk, transport::AeadSuite::Aes256Gcm, true, b"test-client-random", &NoKeyLog);
+        let mut server = transport::RecordLayer::new::<DefaultSuite>(&sk, transport::AeadSuite::Aes256Gcm, false, b"test-client-random", &NoKeyLog);
+
+        let (seq, mut ciphertext) = client.seal(b"", b"data").expect("seal");
+        *ciphertext.last_mut().unwrap() ^= 0xff;
+
+        let result = server.open(seq, b"", &ciphertext);
+        assert_eq!(result, Err(transport::RecordError::AuthenticationFailed));
+    }
+
+    #[test]
+    fn test_record_layer_rejects_out_of_order_and_replayed_records() {
+        let sk = [44u8; 32];
+        let mut client = transport::RecordLayer::new::<DefaultSuite>(&sk, transport::AeadSuite::Aes256Gcm, true, b"test-client-random", &NoKeyLog);
+        let mut server = transport::RecordLayer::new::<DefaultSuite>(&sk, transport::AeadSuite::Aes256Gcm, false, b"test-client-random", &NoKeyLog);
+
+        let (_seq0, ct0) = client.seal(b"", b"first").expect("seal");
+        let (seq1, ct1) = client.seal(b"", b"second").expect("seal");
+
+        // Delivering record #1 before record #0 is out of order.
+        let result = server.open(seq1, b"", &ct1);
+        assert_eq!(result, Err(transport::RecordError::OutOfOrder));
+
+        // Open #0 properly, then try to replay it - its sequence number
+        // has already been consumed.
+        assert!(server.open(0, b"", &ct0).is_ok());
+        let replay = server.open(0, b"", &ct0);
+        assert_eq!(replay, Err(transport::RecordError::OutOfOrder));
+    }
+
+    #[test]
+    fn test_record_layer_key_update_changes_ciphertext() {
+        let sk = [55u8; 32];
+        let mut client = transport::RecordLayer::new::<DefaultSuite>(&sk, transport::AeadSuite::Aes256Gcm, true, b"test-client-random", &NoKeyLog);
+
+        let (_seq_before, ciphertext_before) = client.seal(b"", b"same plaintext").expect("seal");
+        client.update_keys::<DefaultSuite>(b"test-client-random", &NoKeyLog);
+        let (seq_after, ciphertext_after) = client.seal(b"", b"same plaintext").expect("seal");
+
+        assert_eq!(seq_after, 0, "key update resets the sequence number for the new secret");
+        assert_ne!(ciphertext_before, ciphertext_after);
+    }
+}
@@ -0,0 +1,85 @@
+//! PSK+DHE session resumption, modeled on TLS 1.3's `psk_dhe_ke` mode: a
+//! successful full handshake hands the client a `resumption_psk`; the next
+//! connection skips the OPRF+envelope dance, but both sides still
+//! contribute a fresh ephemeral DH share, and the session key mixes the
+//! PSK with that DH output. That gives resumption forward secrecy (a
+//! later PSK leak can't retroactively recover past sessions, since each
+//! session also needed its own ephemeral secrets) while still requiring
+//! the PSK to authenticate the peer at all.
+
+use crate::ciphersuite::{CipherSuite, Group, KdfHash};
+use crate::{ClientEphemeralKeys, ServerEphemeralKeys};
+
+/// Size of a PSK identity, used by the client to tell the server which
+/// stored PSK to look up.
+pub const PSK_IDENTITY_LEN: usize = 16;
+
+/// A resumption PSK, together with the opaque identity the client presents
+/// to the server so it can find the matching PSK again.
+#[derive(Debug, Clone)]
+pub struct ResumptionTicket {
+    pub identity: [u8; PSK_IDENTITY_LEN],
+    pub psk: Vec<u8>,
+}
+
+/// Derive a fresh resumption PSK from a just-completed session's shared
+/// secret. Called once key confirmation has succeeded on both sides.
+pub fn derive_resumption_psk<C: CipherSuite>(
+    sk: &[u8; 32],
+    identity: [u8; PSK_IDENTITY_LEN],
+) -> ResumptionTicket {
+    let psk = C::Hash::hkdf_expand(sk, b"OPAQUE-Resumption-PSK", 32);
+    ResumptionTicket { identity, psk }
+}
+
+/// Rotate a PSK without running a full handshake again, e.g. on a fixed
+/// schedule or after suspected exposure. The new PSK only depends on the
+/// old one, not on any session key, so rotation never needs `sk`.
+pub fn rotate_psk<C: CipherSuite>(ticket: &ResumptionTicket) -> ResumptionTicket {
+    let psk = C::Hash::hkdf_expand(&ticket.psk, b"OPAQUE-Resumption-Rotate", 32);
+    ResumptionTicket { identity: ticket.identity, psk }
+}
+
+/// `HKDF-Extract(psk, DH(X,Y))`, expanded under `label` - the PSK salts
+/// the DHE extract step the way a TLS 1.3 handshake secret salts in the
+/// (EC)DHE shared secret on top of the early secret.
+fn derive_resumption_sk<C: CipherSuite>(psk: &[u8], dh_bytes: &[u8], label: &[u8]) -> [u8; 32] {
+    let sk_bytes = C::Hash::hkdf_extract_and_expand(psk, dh_bytes, label, 32);
+    let mut sk = [0u8; 32];
+    sk.copy_from_slice(&sk_bytes);
+    sk
+}
+
+/// Client side of resumption: combine the PSK with `DH(x, Y)` to get the
+/// resumed session key.
+pub fn resumption_client_finish<C: CipherSuite>(
+    psk: &[u8],
+    client_ephemeral: &ClientEphemeralKeys<C>,
+    server_ephemeral_public: &<C::KeGroup as Group>::Point,
+) -> [u8; 32] {
+    let dh = C::KeGroup::point_mul(server_ephemeral_public, &client_ephemeral.secret);
+    derive_resumption_sk::<C>(psk, &C::KeGroup::point_to_bytes(&dh), b"OPAQUE-Resumption-SK")
+}
+
+/// Server side of resumption: combine the PSK with `DH(X, y)` to get the
+/// resumed session key - the same value as `resumption_client_finish`
+/// computes, since `DH(x, Y) == DH(X, y)`.
+pub fn resumption_server_finish<C: CipherSuite>(
+    psk: &[u8],
+    server_ephemeral: &ServerEphemeralKeys<C>,
+    client_ephemeral_public: &<C::KeGroup as Group>::Point,
+) -> [u8; 32] {
+    let dh = C::KeGroup::point_mul(client_ephemeral_public, &server_ephemeral.secret);
+    derive_resumption_sk::<C>(psk, &C::KeGroup::point_to_bytes(&dh), b"OPAQUE-Resumption-SK")
+}
+
+/// Derive the server's 0.5-RTT traffic key from the resumed session key,
+/// so the server can start sending response data as soon as it has
+/// computed `sk` - before it has received (or verified) the client's key
+/// confirmation MAC. Authentication is still only final once the client's
+/// MAC has been checked with `verify_client_mac`; data sent under this key
+/// should be treated accordingly (e.g. not anything that requires the
+/// client to already be authenticated).
+pub fn server_early_traffic_key<C: CipherSuite>(sk: &[u8; 32]) -> Vec<u8> {
+    C::Hash::hkdf_expand(sk, b"OPAQUE-Resumption-0.5RTT-ServerData", 32)
+}
@@ -0,0 +1,68 @@
+//! The OPAQUE "slow hash" stage applied to the raw OPRF output before it is
+//! used as keying material. Without it, a stolen `RegistrationRecord` plus
+//! an OPRF key reduces password guessing to one HKDF-cheap hash per guess;
+//! a memory-hard step closes that gap the way a salted password hash would
+//! for a plain password database.
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+/// Hardens a raw OPRF output into `randomized_pwd`. Mirrors the
+/// `SlowHash` associated type in the `opaque-ke` ecosystem, where the
+/// default is Argon2id and tests swap in a no-op to stay fast.
+pub trait SlowHash {
+    fn hash(&self, oprf_output: &[u8]) -> Vec<u8>;
+}
+
+/// Argon2id with OPAQUE's recommended defaults (m=19456 KiB, t=2, p=1).
+/// The salt is derived from the OPRF output itself via HKDF, so there is
+/// nothing extra to store alongside the registration record - the same
+/// password re-derives the same salt deterministically.
+pub struct Argon2Hash {
+    params: Params,
+}
+
+impl Argon2Hash {
+    /// `m_cost_kib`/`t_cost`/`p_cost` are the Argon2 memory (KiB), time
+    /// (passes) and parallelism cost parameters, tunable per deployment.
+    pub fn with_params(m_cost_kib: u32, t_cost: u32, p_cost: u32) -> Self {
+        let params = Params::new(m_cost_kib, t_cost, p_cost, Some(32))
+            .expect("valid Argon2 parameters");
+        Self { params }
+    }
+}
+
+impl Default for Argon2Hash {
+    /// m=19456 KiB, t=2, p=1 - the OPAQUE draft's recommended Argon2id cost.
+    fn default() -> Self {
+        Self::with_params(19456, 2, 1)
+    }
+}
+
+impl SlowHash for Argon2Hash {
+    fn hash(&self, oprf_output: &[u8]) -> Vec<u8> {
+        let hkdf = Hkdf::<Sha256>::new(None, oprf_output);
+        let mut salt = [0u8; 16];
+        hkdf.expand(b"OPAQUE-SlowHash-Salt", &mut salt)
+            .expect("HKDF expand failed");
+
+        let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params.clone());
+        let mut randomized_pwd = vec![0u8; 32];
+        argon2
+            .hash_password_into(oprf_output, &salt, &mut randomized_pwd)
+            .expect("Argon2id hashing failed");
+        randomized_pwd
+    }
+}
+
+/// Skips the memory-hard step entirely, returning the OPRF output
+/// unchanged. Only for tests, where Argon2id's cost would make the suite
+/// slow for no benefit.
+pub struct NoOpHash;
+
+impl SlowHash for NoOpHash {
+    fn hash(&self, oprf_output: &[u8]) -> Vec<u8> {
+        oprf_output.to_vec()
+    }
+}